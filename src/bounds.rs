@@ -0,0 +1,64 @@
+use cgmath::{Array, Vector3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+  pub min: Vector3<f32>,
+  pub max: Vector3<f32>,
+}
+
+impl Aabb {
+  // Contains nothing, growing it by any point gives a box around just that point
+  pub fn empty() -> Self {
+    Self {
+      min: Vector3::from_value(f32::INFINITY),
+      max: Vector3::from_value(f32::NEG_INFINITY),
+    }
+  }
+
+  pub fn from_points<I>(points: I) -> Self
+  where
+    I: IntoIterator<Item = Vector3<f32>>,
+  {
+    points.into_iter().fold(Self::empty(), Self::grow)
+  }
+
+  pub fn center(&self) -> Vector3<f32> {
+    (self.min + self.max) * 0.5
+  }
+
+  // Corner i takes max x when bit 0 is set, max y for bit 1 and max z for bit 2
+  pub fn corners(&self) -> [Vector3<f32>; 8] {
+    let mut corners = [self.min; 8];
+
+    for (i, corner) in corners.iter_mut().enumerate() {
+      if i & 1 != 0 { corner.x = self.max.x; }
+      if i & 2 != 0 { corner.y = self.max.y; }
+      if i & 4 != 0 { corner.z = self.max.z; }
+    }
+
+    corners
+  }
+
+  pub fn grow(self, point: Vector3<f32>) -> Self {
+    Self {
+      min: Vector3::new(self.min.x.min(point.x), self.min.y.min(point.y), self.min.z.min(point.z)),
+      max: Vector3::new(self.max.x.max(point.x), self.max.y.max(point.y), self.max.z.max(point.z)),
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+  }
+
+  pub fn size(&self) -> Vector3<f32> {
+    self.max - self.min
+  }
+
+  pub fn union(self, other: Aabb) -> Self {
+    if other.is_empty() {
+      return self;
+    }
+
+    self.grow(other.min).grow(other.max)
+  }
+}
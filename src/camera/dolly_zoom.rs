@@ -0,0 +1,31 @@
+use cgmath::{Angle, Deg, Rad};
+use std::time::Duration;
+
+const FOV_MAX: f32 = 90.0;
+const FOV_MIN: f32 = 20.0;
+const PERIOD: f32 = 6.0;
+
+// Swings the field of view while the distance follows it, so the view keeps the same width at
+// the target and only the perspective changes
+#[derive(Debug, Default)]
+pub struct DollyZoom {
+  elapsed: f32,
+  width: Option<f32>,
+}
+
+impl DollyZoom {
+  // Field of view after dt, and the target distance that keeps the width at it. The first call keeps
+  // the width of the current view, distance from the target at fovy, so a camera placed by --scene
+  // or a camera path is picked up
+  pub fn advance(&mut self, dt: Duration, distance: f32, fovy: Deg<f32>) -> (Deg<f32>, f32) {
+    let width = *self.width.get_or_insert_with(|| 2.0 * distance * Rad::from(fovy / 2.0).tan());
+
+    self.elapsed += dt.as_secs_f32();
+
+    let phase = Rad::full_turn() * (self.elapsed / PERIOD);
+    let fovy = Deg(FOV_MIN + (FOV_MAX - FOV_MIN) * 0.5 * (1.0 - phase.cos()));
+    let distance = width / (2.0 * Rad::from(fovy / 2.0).tan());
+
+    (fovy, distance)
+  }
+}
@@ -72,6 +72,8 @@ pub struct FPSCameraController {
   amount_backward: f32,
   amount_up: f32,
   amount_down: f32,
+  invert_x: f32,
+  invert_y: f32,
   rotate_horizontal: f32,
   rotate_vertical: f32,
   scroll: f32,
@@ -88,6 +90,8 @@ impl FPSCameraController {
       amount_backward: 0.0,
       amount_up: 0.0,
       amount_down: 0.0,
+      invert_x: 1.0,
+      invert_y: 1.0,
       rotate_horizontal: 0.0,
       rotate_vertical: 0.0,
       scroll: 0.0,
@@ -95,6 +99,11 @@ impl FPSCameraController {
       sensitivity,
     }
   }
+
+  pub fn set_inversion(&mut self, invert_x: bool, invert_y: bool) {
+    self.invert_x = if invert_x { -1.0 } else { 1.0 };
+    self.invert_y = if invert_y { -1.0 } else { 1.0 };
+  }
 }
 
 impl CameraController<FPSCamera> for FPSCameraController {
@@ -135,8 +144,8 @@ impl CameraController<FPSCamera> for FPSCameraController {
   }
 
   fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
-    self.rotate_horizontal = mouse_dx as f32;
-    self.rotate_vertical = mouse_dy as f32;
+    self.rotate_horizontal = mouse_dx as f32 * self.invert_x;
+    self.rotate_vertical = mouse_dy as f32 * self.invert_y;
   }
 
   fn process_scroll(&mut self, delta: &MouseScrollDelta) {
@@ -12,11 +12,17 @@ use winit::event::{
 
 use crate::projection::Projection;
 
+pub mod dolly_zoom;
 pub mod fps_camera;
 pub mod orbit_camera;
+pub mod path;
+pub mod turntable;
 
+pub use dolly_zoom::DollyZoom;
 pub use fps_camera::{FPSCamera, FPSCameraController};
-pub use orbit_camera::{OrbitCamera, OrbitCameraController};
+pub use orbit_camera::{OrbitCamera, OrbitCameraController, ZoomMode};
+pub use path::CameraPath;
+pub use turntable::Turntable;
 
 pub trait Camera {
   fn from_position(position: Point3<f32>) -> Self;
@@ -72,6 +78,12 @@ impl CameraUniform {
     }
   }
 
+  // For views that aren't driven by a Camera, like the minimap
+  pub fn set_view_proj(&mut self, position: Point3<f32>, view_proj: Matrix4<f32>) {
+    self.view_position = position.to_homogeneous().into();
+    self.view_proj = view_proj.into();
+  }
+
   pub fn update_view_proj<C: Camera>(&mut self, camera: &C, projection: &Projection) {
     self.view_position = camera.get_position().to_homogeneous().into();
     self.view_proj = (projection.calc_matrix() * camera.projection()).into();
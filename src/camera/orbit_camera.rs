@@ -1,8 +1,12 @@
 use cgmath::{
+  Deg,
   EuclideanSpace,
   InnerSpace,
   Matrix4,
   Point3,
+  Quaternion,
+  Rad,
+  Rotation3,
   Vector3,
 };
 use std::time::Duration;
@@ -13,6 +17,22 @@ use winit::{
 
 use crate::camera::{Camera, CameraController};
 
+const FOV_ZOOM_SPEED: f32 = 20.0;
+const ORBIT_STEPS_PER_SECOND: f32 = 4.0;
+const PIXELS_PER_LINE: f32 = 40.0;
+const RETARGET_SNAP_DISTANCE: f32 = 0.001;
+const RETARGET_SPEED: f32 = 8.0;
+// Keeps walk mode from looking straight up or down, where yaw has no meaning
+const WALK_PITCH_LIMIT: f32 = 1.5;
+// Radians per pixel of mouse motion per unit of sensitivity when looking around in walk mode
+const WALK_TURN_SCALE: f32 = 0.1;
+
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ZoomMode {
+  Distance,
+  Fov,
+}
+
 #[derive(Debug)]
 pub struct OrbitCamera {
   eye: Point3<f32>,
@@ -31,6 +51,15 @@ impl OrbitCamera {
       up,
     }
   }
+
+  pub fn look_at(&mut self, eye: Point3<f32>, target: Point3<f32>) {
+    self.eye = eye;
+    self.target = target;
+  }
+
+  pub fn target(&self) -> Point3<f32> {
+    self.target
+  }
 }
 
 impl Camera for OrbitCamera {
@@ -55,11 +84,31 @@ pub struct OrbitCameraController {
   amount_backward: f32,
   amount_up: f32,
   amount_down: f32,
+  fov_delta: f32,
+  invert_x: f32,
+  invert_y: f32,
+  orbit_down: f32,
+  orbit_left: f32,
+  orbit_pitch: f32,
+  orbit_right: f32,
+  orbit_step: Rad<f32>,
+  orbit_up: f32,
+  orbit_yaw: f32,
+  pan_horizontal: f32,
+  pan_vertical: f32,
+  pan_speed: f32,
+  panning: bool,
+  retarget: Option<Point3<f32>>,
+  retarget_smooth: bool,
   rotate_horizontal: f32,
   rotate_vertical: f32,
   scroll: f32,
+  shift_held: bool,
   speed: f32,
   sensitivity: f32,
+  // Eye height above y = 0 while walking
+  walk_height: Option<f32>,
+  zoom_mode: ZoomMode,
 }
 
 impl OrbitCameraController {
@@ -71,13 +120,96 @@ impl OrbitCameraController {
       amount_backward: 0.0,
       amount_up: 0.0,
       amount_down: 0.0,
+      fov_delta: 0.0,
+      invert_x: 1.0,
+      invert_y: 1.0,
+      orbit_down: 0.0,
+      orbit_left: 0.0,
+      orbit_pitch: 0.0,
+      orbit_right: 0.0,
+      orbit_step: Deg(15.0).into(),
+      orbit_up: 0.0,
+      orbit_yaw: 0.0,
+      pan_horizontal: 0.0,
+      pan_vertical: 0.0,
+      pan_speed: 0.002,
+      panning: false,
+      retarget: None,
+      retarget_smooth: true,
       rotate_horizontal: 0.0,
       rotate_vertical: 0.0,
       scroll: 0.0,
+      shift_held: false,
       speed,
       sensitivity,
+      walk_height: None,
+      zoom_mode: ZoomMode::Distance,
     }
   }
+
+  // Moves the orbit pivot to target, carrying the eye along so the view distance is kept
+  pub fn retarget(&mut self, target: Point3<f32>, smooth: bool) {
+    self.retarget = Some(target);
+    self.retarget_smooth = smooth;
+  }
+
+  pub fn set_inversion(&mut self, invert_x: bool, invert_y: bool) {
+    self.invert_x = if invert_x { -1.0 } else { 1.0 };
+    self.invert_y = if invert_y { -1.0 } else { 1.0 };
+  }
+
+  pub fn set_orbit_step<A: Into<Rad<f32>>>(&mut self, step: A) {
+    self.orbit_step = step.into();
+  }
+
+  // Looks around from the eye and moves along the ground at a fixed height, instead of orbiting
+  pub fn set_walk(&mut self, height: Option<f32>) {
+    self.walk_height = height;
+  }
+
+  pub fn set_zoom_mode(&mut self, zoom_mode: ZoomMode) {
+    self.zoom_mode = zoom_mode;
+  }
+
+  // Field of view change in degrees requested by scrolling in fov zoom mode
+  pub fn take_fov_delta(&mut self) -> Deg<f32> {
+    let delta = Deg(self.fov_delta);
+
+    self.fov_delta = 0.0;
+
+    delta
+  }
+
+  // Mouse motion pans the target instead of orbiting while set (or while shift is held)
+  pub fn set_panning(&mut self, panning: bool) {
+    self.panning = panning;
+  }
+
+  fn walk(&mut self, camera: &mut OrbitCamera, height: f32, dt: f32) {
+    let direction = camera.target - camera.eye;
+    let distance = direction.magnitude().max(1.0);
+    let flat = Vector3::new(direction.x, 0.0, direction.z);
+    let yaw = Rad(flat.z.atan2(flat.x)) + Rad(self.rotate_horizontal * self.sensitivity * WALK_TURN_SCALE);
+    let pitch = Rad(direction.y.atan2(flat.magnitude())) - Rad(self.rotate_vertical * self.sensitivity * WALK_TURN_SCALE);
+    let pitch = Rad(pitch.0.clamp(-WALK_PITCH_LIMIT, WALK_PITCH_LIMIT));
+    let (yaw_sin, yaw_cos) = yaw.0.sin_cos();
+    let (pitch_sin, pitch_cos) = pitch.0.sin_cos();
+    let forward = Vector3::new(yaw_cos, 0.0, yaw_sin);
+    let right = forward.cross(camera.up);
+    let step = (self.amount_forward - self.amount_backward + self.scroll * 0.1) * forward
+      + (self.amount_right - self.amount_left) * right;
+
+    camera.eye += step * self.speed * dt;
+    camera.eye.y = height;
+    camera.target = camera.eye + Vector3::new(pitch_cos * yaw_cos, pitch_sin, pitch_cos * yaw_sin) * distance;
+
+    self.pan_horizontal = 0.0;
+    self.pan_vertical = 0.0;
+    self.retarget = None;
+    self.rotate_horizontal = 0.0;
+    self.rotate_vertical = 0.0;
+    self.scroll = 0.0;
+  }
 }
 
 impl CameraController<OrbitCamera> for OrbitCameraController {
@@ -89,28 +221,49 @@ impl CameraController<OrbitCamera> for OrbitCameraController {
     let amount = if state == ElementState::Pressed { 1.0 } else { 0.0 };
 
     match key {
-      VirtualKeyCode::W | VirtualKeyCode::Up => {
+      VirtualKeyCode::W | VirtualKeyCode::PageUp => {
         self.amount_forward = amount;
         true
       }
-      VirtualKeyCode::S | VirtualKeyCode::Down => {
+      VirtualKeyCode::S | VirtualKeyCode::PageDown => {
         self.amount_backward = amount;
         true
       }
-      VirtualKeyCode::A | VirtualKeyCode::Left => {
+      VirtualKeyCode::A => {
         self.amount_left = amount;
         true
       }
-      VirtualKeyCode::D | VirtualKeyCode::Right => {
+      VirtualKeyCode::D => {
         self.amount_right = amount;
         true
       }
+      VirtualKeyCode::Left => {
+        if self.orbit_left == 0.0 { self.orbit_yaw -= amount; }
+        self.orbit_left = amount;
+        true
+      }
+      VirtualKeyCode::Right => {
+        if self.orbit_right == 0.0 { self.orbit_yaw += amount; }
+        self.orbit_right = amount;
+        true
+      }
+      VirtualKeyCode::Up => {
+        if self.orbit_up == 0.0 { self.orbit_pitch += amount; }
+        self.orbit_up = amount;
+        true
+      }
+      VirtualKeyCode::Down => {
+        if self.orbit_down == 0.0 { self.orbit_pitch -= amount; }
+        self.orbit_down = amount;
+        true
+      }
       VirtualKeyCode::Space => {
         self.amount_up = amount;
         true
       }
+      // Shift is the pan modifier, so it no longer doubles as move down
       VirtualKeyCode::LShift => {
-        self.amount_down = amount;
+        self.shift_held = state == ElementState::Pressed;
         true
       }
       _ => false,
@@ -118,22 +271,73 @@ impl CameraController<OrbitCamera> for OrbitCameraController {
   }
 
   fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
-    self.rotate_horizontal = mouse_dx as f32;
-    self.rotate_vertical = mouse_dy as f32;
+    if self.panning || self.shift_held {
+      self.pan_horizontal = mouse_dx as f32;
+      self.pan_vertical = mouse_dy as f32;
+    } else {
+      self.rotate_horizontal = mouse_dx as f32 * self.invert_x;
+      self.rotate_vertical = mouse_dy as f32 * self.invert_y;
+    }
   }
 
   fn process_scroll(&mut self, delta: &MouseScrollDelta) {
-    self.scroll = match delta {
+    let scroll = match delta {
       MouseScrollDelta::LineDelta(_, scroll) => *scroll * -0.1,
       MouseScrollDelta::PixelDelta(PhysicalPosition{
         y: scroll,
         ..
       }) => *scroll as f32,
     };
+
+    // Trackpads report pixels, so they're turned into lines before zooming the fov
+    let lines = match delta {
+      MouseScrollDelta::LineDelta(_, lines) => *lines,
+      MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_LINE,
+    };
+
+    match self.zoom_mode {
+      ZoomMode::Distance => self.scroll = scroll,
+      ZoomMode::Fov => self.fov_delta += lines * -0.1 * FOV_ZOOM_SPEED,
+    }
   }
 
   fn update_camera(&mut self, camera: &mut OrbitCamera, dt: Duration) {
     let dt = dt.as_secs_f32();
+
+    if let Some(height) = self.walk_height {
+      self.walk(camera, height, dt);
+      return;
+    }
+
+    if let Some(goal) = self.retarget {
+      let remaining = goal - camera.target;
+      let step = if !self.retarget_smooth || remaining.magnitude() < RETARGET_SNAP_DISTANCE {
+        self.retarget = None;
+        remaining
+      } else {
+        remaining * (RETARGET_SPEED * dt).min(1.0)
+      };
+
+      camera.eye += step;
+      camera.target += step;
+    }
+
+    // Arrow keys step once per press and keep orbiting while held
+    let held_steps = ORBIT_STEPS_PER_SECOND * dt;
+    let yaw = self.orbit_step * (self.orbit_yaw + (self.orbit_right - self.orbit_left) * held_steps);
+    let pitch = self.orbit_step * (self.orbit_pitch + (self.orbit_up - self.orbit_down) * held_steps);
+    let offset = Quaternion::from_axis_angle(camera.up, yaw) * (camera.eye - camera.target);
+    let pitch_axis = offset.cross(camera.up).normalize();
+    let pitched = Quaternion::from_axis_angle(pitch_axis, pitch) * offset;
+
+    camera.eye = if pitched.normalize().dot(camera.up).abs() < 0.99 {
+      camera.target + pitched
+    } else {
+      camera.target + offset
+    };
+    self.orbit_yaw = 0.0;
+    self.orbit_pitch = 0.0;
+
     let forward = camera.target - camera.eye;
     let forward_norm = forward.normalize();
     let forward_mag = forward.magnitude();
@@ -148,7 +352,16 @@ impl CameraController<OrbitCamera> for OrbitCameraController {
 
     camera.eye += self.scroll * 0.01 * forward;
 
+    // Pan along the view plane, scaled by distance so it tracks the cursor at any zoom
+    let pan_right = right.normalize();
+    let pan_up = pan_right.cross(forward_norm);
+    let pan = (pan_up * self.pan_vertical - pan_right * self.pan_horizontal) * self.pan_speed * forward_mag;
+
+    camera.eye += pan;
+    camera.target += pan;
 
+    self.pan_horizontal = 0.0;
+    self.pan_vertical = 0.0;
     self.rotate_horizontal = 0.0;
     self.rotate_vertical = 0.0;
     self.scroll = 0.0;
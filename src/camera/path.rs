@@ -0,0 +1,66 @@
+use anyhow::{bail, Context, Result};
+use cgmath::Point3;
+use serde::Deserialize;
+use std::{
+  cmp::Ordering,
+  fs,
+  path::Path,
+  time::Duration,
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Keyframe {
+  pub position: [f32; 3],
+  #[serde(default)]
+  pub target: [f32; 3],
+  pub time: f32,
+}
+
+// Keyframed flythrough, read from a JSON list and played back in real time
+#[derive(Debug)]
+pub struct CameraPath {
+  elapsed: f32,
+  keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+  pub fn load(path: &Path) -> Result<Self> {
+    let text = fs::read_to_string(path).with_context(|| format!("Couldn't read camera path {}", path.display()))?;
+    let mut keyframes: Vec<Keyframe> = serde_json::from_str(&text)
+      .with_context(|| format!("Couldn't parse camera path {}", path.display()))?;
+
+    if keyframes.is_empty() {
+      bail!("Camera path {} has no keyframes", path.display());
+    }
+    keyframes.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(Ordering::Equal));
+
+    Ok(Self { elapsed: 0.0, keyframes })
+  }
+
+  pub fn advance(&mut self, dt: Duration) {
+    self.elapsed += dt.as_secs_f32();
+  }
+
+  pub fn finished(&self) -> bool {
+    self.keyframes.last().map_or(true, |last| self.elapsed > last.time)
+  }
+
+  // Eye and target at the current time, linear between the keyframes either side of it
+  pub fn sample(&self) -> (Point3<f32>, Point3<f32>) {
+    let next = self.keyframes.iter().position(|keyframe| keyframe.time > self.elapsed);
+    let (from, to) = match next {
+      Some(0) => (&self.keyframes[0], &self.keyframes[0]),
+      Some(index) => (&self.keyframes[index - 1], &self.keyframes[index]),
+      None => {
+        let last = &self.keyframes[self.keyframes.len() - 1];
+        (last, last)
+      }
+    };
+    let span = to.time - from.time;
+    let t = if span > 0.0 { (self.elapsed - from.time) / span } else { 0.0 };
+    let lerp = |a: [f32; 3], b: [f32; 3]| Point3::from(a) + (Point3::from(b) - Point3::from(a)) * t;
+
+    (lerp(from.position, to.position), lerp(from.target, to.target))
+  }
+}
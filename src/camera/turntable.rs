@@ -0,0 +1,35 @@
+use cgmath::{Angle, Quaternion, Rad, Rotation3, Vector3};
+
+// One full turn about the vertical axis split evenly over a fixed number of frames. Frame i is at
+// i / frames of a turn, so the last frame stops one step short of the first and the sequence loops
+#[derive(Debug)]
+pub struct Turntable {
+  frame: u32,
+  frames: u32,
+  offset: Option<Vector3<f32>>,
+}
+
+impl Turntable {
+  pub fn new(frames: u32) -> Self {
+    Self {
+      frame: 0,
+      frames,
+      offset: None,
+    }
+  }
+
+  // Eye offset from the target for the next frame. The first call keeps current, the eye's position
+  // relative to the target, as the start of the turn, so a camera placed by --scene is picked up
+  pub fn advance(&mut self, current: Vector3<f32>) -> Vector3<f32> {
+    let offset = *self.offset.get_or_insert(current);
+    let angle = Rad::full_turn() * (self.frame as f32 / self.frames as f32);
+
+    self.frame += 1;
+
+    Quaternion::from_angle_y(angle) * offset
+  }
+
+  pub fn finished(&self) -> bool {
+    self.frame >= self.frames
+  }
+}
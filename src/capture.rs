@@ -0,0 +1,160 @@
+use anyhow::{anyhow, bail, Result};
+use std::{
+  fs,
+  num::NonZeroU32,
+  path::{Path, PathBuf},
+};
+
+const MIN_FRAME_DIGITS: usize = 4;
+
+// Hands out numbered paths for a fixed length frame sequence
+pub struct FrameRecorder {
+  count: u32,
+  dir: PathBuf,
+  frame: u32,
+}
+
+impl FrameRecorder {
+  pub fn new(dir: PathBuf, count: u32) -> Result<Self> {
+    fs::create_dir_all(&dir)?;
+
+    Ok(Self { count, dir, frame: 0 })
+  }
+
+  pub fn finished(&self) -> bool {
+    self.frame >= self.count
+  }
+
+  // Zero padded so the frames sort and glob in order for ffmpeg
+  pub fn next_path(&mut self) -> PathBuf {
+    let digits = self.count.to_string().len().max(MIN_FRAME_DIGITS);
+    let path = self.dir.join(format!("frame_{:0width$}.png", self.frame, width = digits));
+
+    self.frame += 1;
+
+    path
+  }
+}
+
+// Renders one frame into an offscreen copy of the surface and writes it out as a PNG
+pub fn capture_png<F>(
+  device: &wgpu::Device,
+  queue: &wgpu::Queue,
+  config: &wgpu::SurfaceConfiguration,
+  path: &Path,
+  render: F,
+) -> Result<()>
+where
+  F: FnOnce(&wgpu::TextureView),
+{
+  let texture = device.create_texture(&wgpu::TextureDescriptor {
+    label: Some("Capture Texture"),
+    size: wgpu::Extent3d {
+      width: config.width,
+      height: config.height,
+      depth_or_array_layers: 1,
+    },
+    mip_level_count: 1,
+    sample_count: 1,
+    dimension: wgpu::TextureDimension::D2,
+    format: config.format,
+    usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+  });
+  let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+  render(&view);
+  save_png(device, queue, config, &texture, path)
+}
+
+// Copies a texture the size and format of the surface back from the GPU and writes it out as a PNG.
+// The texture needs COPY_SRC usage.
+pub fn save_png(
+  device: &wgpu::Device,
+  queue: &wgpu::Queue,
+  config: &wgpu::SurfaceConfiguration,
+  texture: &wgpu::Texture,
+  path: &Path,
+) -> Result<()> {
+  let swap_red_blue = match config.format {
+    wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+    wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+    format => bail!("Can't capture frames in {:?}", format),
+  };
+  let size = wgpu::Extent3d {
+    width: config.width,
+    height: config.height,
+    depth_or_array_layers: 1,
+  };
+
+  // Buffer rows have to be padded out to the copy alignment
+  let row_bytes = config.width * 4;
+  let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+  let padded_row_bytes = (row_bytes + alignment - 1) / alignment * alignment;
+  let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+    label: Some("Capture Buffer"),
+    size: (padded_row_bytes * config.height) as wgpu::BufferAddress,
+    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+    mapped_at_creation: false,
+  });
+  let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+    label: Some("Capture Encoder"),
+  });
+
+  encoder.copy_texture_to_buffer(
+    wgpu::ImageCopyTexture {
+      aspect: wgpu::TextureAspect::All,
+      texture,
+      mip_level: 0,
+      origin: wgpu::Origin3d::ZERO,
+    },
+    wgpu::ImageCopyBuffer {
+      buffer: &buffer,
+      layout: wgpu::ImageDataLayout {
+        offset: 0,
+        bytes_per_row: NonZeroU32::new(padded_row_bytes),
+        rows_per_image: NonZeroU32::new(config.height),
+      },
+    },
+    size,
+  );
+  queue.submit(std::iter::once(encoder.finish()));
+
+  let slice = buffer.slice(..);
+  let mapping = slice.map_async(wgpu::MapMode::Read);
+
+  device.poll(wgpu::Maintain::Wait);
+  pollster::block_on(mapping)?;
+
+  let mut pixels = Vec::with_capacity((row_bytes * config.height) as usize);
+
+  for row in slice.get_mapped_range().chunks(padded_row_bytes as usize) {
+    pixels.extend_from_slice(&row[..row_bytes as usize]);
+  }
+  buffer.unmap();
+
+  if swap_red_blue {
+    for pixel in pixels.chunks_exact_mut(4) {
+      pixel.swap(0, 2);
+    }
+  }
+
+  image::RgbaImage::from_raw(config.width, config.height, pixels)
+    .ok_or_else(|| anyhow!("Captured frame doesn't match the surface size"))?
+    .save(path)?;
+
+  Ok(())
+}
+
+// Picks prefix_YYYYMMDD_HHMMSS.extension in dir, with a counter if that second is already taken
+pub fn timestamped_path(dir: &Path, prefix: &str, extension: &str) -> PathBuf {
+  let stem = format!("{}_{}", prefix, chrono::Local::now().format("%Y%m%d_%H%M%S"));
+  let mut path = dir.join(format!("{}.{}", stem, extension));
+  let mut counter = 1;
+
+  while path.exists() {
+    path = dir.join(format!("{}_{}.{}", stem, counter, extension));
+    counter += 1;
+  }
+
+  path
+}
@@ -1,3 +1,5 @@
+use anyhow::{anyhow, bail, Result};
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct ColorUniform {
@@ -14,3 +16,56 @@ impl From<ColorUniform> for wgpu::Color {
     }
   }
 }
+
+// Parses #rrggbb into linear color channels so it matches the sRGB surface
+pub fn parse_hex(hex: &str) -> Result<[f32; 3]> {
+  let digits = hex.trim_start_matches('#');
+
+  if digits.len() != 6 || !digits.is_ascii() {
+    bail!("Expected a color like #rrggbb, got {}", hex);
+  }
+
+  let channel = |i: usize| -> Result<f32> {
+    let value = u8::from_str_radix(&digits[i..i + 2], 16)? as f32 / 255.0;
+
+    Ok(srgb_to_linear(value))
+  };
+
+  Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+// Parses INDEX:#rrggbb, used to pick out one mesh of a model
+pub fn parse_indexed_hex(value: &str) -> Result<(usize, [f32; 3])> {
+  let (index, hex) = value.split_once(':')
+    .ok_or_else(|| anyhow!("Expected INDEX:#rrggbb, got {}", value))?;
+
+  Ok((index.parse()?, parse_hex(hex)?))
+}
+
+pub fn srgb_to_linear(value: f32) -> f32 {
+  if value <= 0.04045 {
+    value / 12.92
+  } else {
+    ((value + 0.055) / 1.055).powf(2.4)
+  }
+}
+
+// Approximate linear color of a black body at a temperature in kelvin, fit for 1000 to 40000
+pub fn temperature_to_rgb(kelvin: f32) -> [f32; 3] {
+  let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+  let red = if t <= 66.0 { 255.0 } else { 329.699 * (t - 60.0).powf(-0.133_204_76) };
+  let green = if t <= 66.0 {
+    99.470_8 * t.ln() - 161.119_57
+  } else {
+    288.122_17 * (t - 60.0).powf(-0.075_514_85)
+  };
+  let blue = if t >= 66.0 {
+    255.0
+  } else if t <= 19.0 {
+    0.0
+  } else {
+    138.517_73 * (t - 10.0).ln() - 305.044_8
+  };
+
+  [red, green, blue].map(|channel: f32| srgb_to_linear((channel / 255.0).clamp(0.0, 1.0)))
+}
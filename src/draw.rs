@@ -9,7 +9,7 @@ pub trait DrawModel<'a> {
   fn draw_mesh(
     &mut self,
     mesh: &'a Mesh,
-    ambient: &'a wgpu::BindGroup,
+    shading: &'a wgpu::BindGroup,
     camera: &'a wgpu::BindGroup,
     light: &'a wgpu::BindGroup,
   );
@@ -17,14 +17,14 @@ pub trait DrawModel<'a> {
     &mut self,
     mesh: &'a Mesh,
     instances: Range<u32>,
-    ambient: &'a wgpu::BindGroup,
+    shading: &'a wgpu::BindGroup,
     camera: &'a wgpu::BindGroup,
     light: &'a wgpu::BindGroup,
   );
   fn draw_model(
     &mut self,
     model: &'a Model,
-    ambient: &'a wgpu::BindGroup,
+    shading: &'a wgpu::BindGroup,
     camera: &'a wgpu::BindGroup,
     light: &'a wgpu::BindGroup,
   );
@@ -32,7 +32,7 @@ pub trait DrawModel<'a> {
     &mut self,
     model: &'a Model,
     instances: Range<u32>,
-    ambient: &'a wgpu::BindGroup,
+    shading: &'a wgpu::BindGroup,
     camera: &'a wgpu::BindGroup,
     light: &'a wgpu::BindGroup,
   );
@@ -45,49 +45,53 @@ where
   fn draw_mesh(
     &mut self,
     mesh: &'b Mesh,
-    ambient: &'b wgpu::BindGroup,
+    shading: &'b wgpu::BindGroup,
     camera: &'b wgpu::BindGroup,
     light: &'a wgpu::BindGroup,
   ) {
-    self.draw_mesh_instanced(mesh, 0..1, ambient, camera, light);
+    self.draw_mesh_instanced(mesh, 0..1, shading, camera, light);
   }
 
   fn draw_mesh_instanced(
     &mut self,
     mesh: &'b Mesh,
     instances: Range<u32>,
-    ambient: &'b wgpu::BindGroup,
+    shading: &'b wgpu::BindGroup,
     camera: &'b wgpu::BindGroup,
     light: &'a wgpu::BindGroup,
   ){
     self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-    self.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-    self.set_bind_group(0, ambient, &[]);
+    self.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format);
+    self.set_bind_group(0, shading, &[]);
     self.set_bind_group(1, camera, &[]);
     self.set_bind_group(2, light, &[]);
+    self.set_bind_group(3, &mesh.uniform.bind_group, &[]);
     self.draw_indexed(0..mesh.num_elements, 0, instances);
   }
 
   fn draw_model(
     &mut self,
     model: &'b Model,
-    ambient: &'b wgpu::BindGroup,
+    shading: &'b wgpu::BindGroup,
     camera: &'b wgpu::BindGroup,
     light: &'a wgpu::BindGroup,
   ) {
-    self.draw_model_instanced(model, 0..1, ambient, camera, light);
+    self.draw_model_instanced(model, 0..1, shading, camera, light);
   }
 
   fn draw_model_instanced(
     &mut self,
     model: &'b Model,
     instances: Range<u32>,
-    ambient: &'b wgpu::BindGroup,
+    shading: &'b wgpu::BindGroup,
     camera: &'b wgpu::BindGroup,
     light: &'a wgpu::BindGroup,
   ) {
-    for mesh in &model.meshes {
-      self.draw_mesh_instanced(mesh, instances.clone(), ambient, camera, light);
+    let triangle_meshes = model.meshes.iter()
+      .filter(|mesh| mesh.visible && mesh.topology == wgpu::PrimitiveTopology::TriangleList);
+
+    for mesh in triangle_meshes {
+      self.draw_mesh_instanced(mesh, instances.clone(), shading, camera, light);
     }
   }
 }
@@ -143,7 +147,7 @@ where
         light: &'b wgpu::BindGroup,
     ) {
         self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-        self.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        self.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format);
         self.set_bind_group(0, camera, &[]);
         self.set_bind_group(1, light, &[]);
         self.draw_indexed(0..mesh.num_elements, 0, instances);
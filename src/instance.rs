@@ -1,15 +1,63 @@
-use cgmath::{Matrix3, Matrix4, Quaternion, Vector3};
+use anyhow::{bail, Result};
+use cgmath::{Deg, Matrix3, Matrix4, One, Quaternion, Rad, Rotation3, Vector3, Zero};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::ops::Range;
+use wgpu::util::DeviceExt;
 
 use crate::mesh;
 
+const GOLDEN_ANGLE: f32 = 2.399_963;
+const MIN_ORIGIN_DISTANCE: f32 = 0.01;
+
+// Instance buffers are vertex data that update rewrites in place with queue.write_buffer
+pub const BUFFER_USAGE: wgpu::BufferUsages = wgpu::BufferUsages::VERTEX.union(wgpu::BufferUsages::COPY_DST);
+
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub fn vector(&self) -> Vector3<f32> {
+        match self {
+            Axis::X => Vector3::unit_x(),
+            Axis::Y => Vector3::unit_y(),
+            Axis::Z => Vector3::unit_z(),
+        }
+    }
+}
+
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Layout {
+    Grid,
+    Ring,
+    Spiral,
+}
+
+#[derive(Clone, Copy, Debug)]
 pub struct Instance {
     pub position: Vector3<f32>,
     pub rotation: Quaternion<f32>,
+    pub scale: f32,
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self {
+            position: Vector3::zero(),
+            rotation: Quaternion::one(),
+            scale: 1.0,
+        }
+    }
 }
 
 impl Instance {
     pub fn to_raw(&self) -> InstanceRaw {
-        let model = Matrix4::from_translation(self.position) * cgmath::Matrix4::from(self.rotation);
+        let model = Matrix4::from_translation(self.position)
+            * cgmath::Matrix4::from(self.rotation)
+            * Matrix4::from_scale(self.scale);
 
         InstanceRaw {
             model: model.into(),
@@ -18,6 +66,176 @@ impl Instance {
     }
 }
 
+// Ring and spiral instances are turned so their local +z faces away from the origin
+pub fn layout(layout: Layout, count: u32, spacing: f32) -> Vec<Instance> {
+    let facing_outward = |angle: f32| Quaternion::from_angle_y(Rad(std::f32::consts::FRAC_PI_2 - angle));
+
+    match layout {
+        Layout::Grid => {
+            let per_row = (count as f32).sqrt().ceil() as u32;
+
+            (0..count).map(|i| Instance {
+                position: Vector3::new((i % per_row) as f32, 0.0, (i / per_row) as f32) * spacing,
+                ..Default::default()
+            }).collect()
+        }
+        Layout::Ring => {
+            let radius = spacing * count as f32 / std::f32::consts::TAU;
+
+            (0..count).map(|i| {
+                let angle = std::f32::consts::TAU * i as f32 / count as f32;
+
+                Instance {
+                    position: Vector3::new(angle.cos(), 0.0, angle.sin()) * radius,
+                    rotation: facing_outward(angle),
+                    scale: 1.0,
+                }
+            }).collect()
+        }
+        Layout::Spiral => (0..count).map(|i| {
+            let angle = GOLDEN_ANGLE * i as f32;
+            let radius = spacing * (i as f32).sqrt();
+
+            Instance {
+                position: Vector3::new(angle.cos(), 0.0, angle.sin()) * radius,
+                rotation: facing_outward(angle),
+                scale: 1.0,
+            }
+        }).collect(),
+    }
+}
+
+// Applies the same local rotation to every instance, after any rotation the layout gave it
+pub fn orient(instances: &mut [Instance], axis: Axis, angle: Deg<f32>) {
+    let rotation = Quaternion::from_axis_angle(axis.vector(), angle);
+
+    for instance in instances {
+        instance.rotation = instance.rotation * rotation;
+    }
+}
+
+// A scale jitter of 1 or more could shrink an instance to nothing or turn it inside out
+pub fn parse_scatter_scale(value: &str) -> Result<f32> {
+    let scale_jitter = value.parse::<f32>()?;
+
+    if !(0.0..1.0).contains(&scale_jitter) {
+        bail!("Expected a scale jitter from 0 up to but not including 1, got {}", value);
+    }
+
+    Ok(scale_jitter)
+}
+
+// Scatters instances uniformly over a disc on the ground plane, keeping them off the origin
+pub fn scatter(count: u32, radius: f32, seed: u64, rotate: bool, scale_jitter: f32) -> Vec<Instance> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let min_distance = MIN_ORIGIN_DISTANCE.min(radius);
+
+    (0..count).map(|_| {
+        let distance = (rng.gen::<f32>() * (radius * radius - min_distance * min_distance)
+            + min_distance * min_distance).sqrt();
+        let angle: Deg<f32> = Deg(rng.gen_range(0.0..360.0));
+        let direction = Quaternion::from_angle_y(angle) * Vector3::unit_x();
+        let rotation = if rotate {
+            Quaternion::from_angle_y(Deg(rng.gen_range(0.0f32..360.0)))
+        } else {
+            Quaternion::one()
+        };
+        let scale = 1.0 + scale_jitter * rng.gen_range(-1.0..=1.0);
+
+        Instance {
+            position: direction * distance,
+            rotation,
+            scale,
+        }
+    }).collect()
+}
+
+// Updates go into the buffer the in-flight frame isn't reading, then the two swap
+pub struct InstanceBuffer {
+    active: usize,
+    buffers: [wgpu::Buffer; 2],
+    // Instances each buffer has room for
+    capacities: [usize; 2],
+    pub instances: Vec<Instance>,
+    limit: Option<u32>,
+}
+
+impl InstanceBuffer {
+    pub fn new(device: &wgpu::Device, instances: Vec<Instance>) -> Self {
+        let buffers = [
+            Self::create_buffer(device, &instances),
+            Self::create_buffer(device, &instances),
+        ];
+        let capacities = [instances.len().max(1); 2];
+
+        Self { active: 0, buffers, capacities, instances, limit: None }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffers[self.active]
+    }
+
+    // Holds at least one instance, since an empty vertex buffer can't be bound
+    fn create_buffer(device: &wgpu::Device, instances: &[Instance]) -> wgpu::Buffer {
+        let mut instance_data = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+
+        if instance_data.is_empty() {
+            instance_data.push(bytemuck::Zeroable::zeroed());
+        }
+
+        device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Instance Buffer"),
+                contents: bytemuck::cast_slice(&instance_data),
+                usage: BUFFER_USAGE,
+            }
+        )
+    }
+
+    // Room for count instances in a buffer that holds capacity, grown to the next power of two so
+    // a run of small additions doesn't reallocate every time
+    pub fn capacity_for(capacity: usize, count: usize) -> usize {
+        if count > capacity {
+            count.next_power_of_two()
+        } else {
+            capacity
+        }
+    }
+
+    // Writes in place when the back buffer has room, otherwise replaces it with a grown one
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, instances: Vec<Instance>) {
+        let back = 1 - self.active;
+        let capacity = Self::capacity_for(self.capacities[back], instances.len());
+
+        if capacity != self.capacities[back] {
+            self.buffers[back] = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Instance Buffer"),
+                size: (capacity * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+                usage: BUFFER_USAGE,
+                mapped_at_creation: false,
+            });
+            self.capacities[back] = capacity;
+        }
+
+        let instance_data = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+
+        queue.write_buffer(&self.buffers[back], 0, bytemuck::cast_slice(&instance_data));
+        self.active = back;
+        self.instances = instances;
+    }
+
+    // Draws only the first instances, the rest stay in the buffer
+    pub fn set_limit(&mut self, limit: Option<u32>) {
+        self.limit = limit;
+    }
+
+    pub fn range(&self) -> Range<u32> {
+        let count = self.instances.len() as u32;
+
+        0..self.limit.map_or(count, |limit| limit.min(count))
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct InstanceRaw {
@@ -1,3 +1,8 @@
+use anyhow::{anyhow, bail, Result};
+use cgmath::{Angle, Deg, Rad, Vector3};
+
+use crate::color;
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct LightUniform {
@@ -6,3 +11,40 @@ pub struct LightUniform {
     pub color: [f32; 3],
     pub _color_padding: u32,
 }
+
+// Far enough that the point light falls on a scene of a few units like parallel sunlight
+const SUN_DISTANCE: f32 = 50.0;
+
+// Parses HH:MM into hours past midnight
+pub fn parse_time_of_day(value: &str) -> Result<f32> {
+    let (hours, minutes) = value.split_once(':')
+        .ok_or_else(|| anyhow!("Expected a time like HH:MM, got {}", value))?;
+    let hours: u32 = hours.parse()?;
+    let minutes: u32 = minutes.parse()?;
+
+    if hours > 23 || minutes > 59 {
+        bail!("Expected a time between 00:00 and 23:59, got {}", value);
+    }
+
+    Ok(hours as f32 + minutes as f32 / 60.0)
+}
+
+// Sun position and color for a local solar time at an equinox, with north along -z and east along +x.
+// Reddens towards the horizon and fades out once the sun has set.
+pub fn sun(time_of_day: f32, latitude: Deg<f32>) -> ([f32; 3], [f32; 3]) {
+    let hour_angle = Rad::from(Deg(15.0 * (time_of_day - 12.0)));
+    let latitude = Rad::from(latitude);
+    let elevation = Rad((latitude.cos() * hour_angle.cos()).asin());
+    let azimuth = Rad(hour_angle.sin().atan2(hour_angle.cos() * latitude.sin()) + std::f32::consts::PI);
+    let direction = Vector3::new(
+        azimuth.sin() * elevation.cos(),
+        elevation.sin(),
+        -azimuth.cos() * elevation.cos(),
+    );
+    let elevation = Deg::from(elevation).0;
+    let temperature = 2000.0 + 4500.0 * (elevation / 30.0).clamp(0.0, 1.0);
+    let brightness = ((elevation + 2.0) / 8.0).clamp(0.0, 1.0);
+    let color = color::temperature_to_rgb(temperature).map(|channel| channel * brightness);
+
+    ((direction * SUN_DISTANCE).into(), color)
+}
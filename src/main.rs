@@ -1,4 +1,6 @@
+use cgmath::Deg;
 use clap::Parser;
+use std::{path::PathBuf, sync::Arc};
 use winit::{
     event::{
         ElementState,
@@ -11,7 +13,9 @@ use winit::{
     window::WindowBuilder,
 };
 
+mod bounds;
 mod camera;
+mod capture;
 mod color;
 mod draw;
 mod instance;
@@ -20,63 +24,487 @@ mod mesh;
 mod model;
 mod projection;
 mod render;
+mod scene;
+mod shading;
 mod state;
+mod stats;
 mod texture;
+mod ui;
 mod uniform;
 
-use model::ModelPrimitive;
+use camera::{CameraPath, ZoomMode};
+use instance::{Axis, Layout};
+use mesh::ColorSource;
+use model::{ImportOptions, ModelPrimitive, UpAxis};
+use render::{Grid, RenderOptions};
+use scene::Scene;
+use shading::ShadingModel;
 use state::State;
+use texture::DepthFormat;
 
 #[derive(Parser, Debug)]
 #[clap(about, author, version)]
 struct Cli {
+    #[clap(long)]
+    accumulate: bool,
+    #[clap(long)]
+    animate_light_path: bool,
+    #[clap(long, parse(try_from_str = projection::parse_aspect))]
+    aspect: Option<f32>,
+    #[clap(long, parse(from_os_str))]
+    camera_path: Option<PathBuf>,
+    #[clap(long, number_of_values = 3, allow_hyphen_values = true, default_values = &["0", "5", "10"])]
+    camera_pos: Vec<f32>,
+    #[clap(long, number_of_values = 3, allow_hyphen_values = true, default_values = &["0", "0", "0"])]
+    camera_target: Vec<f32>,
+    #[clap(long)]
+    center_light_on_model: bool,
+    #[clap(long)]
+    checker_bg: bool,
+    #[clap(long, number_of_values = 4, allow_hyphen_values = true)]
+    clip_plane: Option<Vec<f32>>,
+    #[clap(long, arg_enum)]
+    color_source: Option<ColorSource>,
+    #[clap(long)]
+    cols: Option<u32>,
     #[clap(long, default_value_t = 8)]
     count: u32,
     #[clap(short, long)]
     cube: bool,
+    #[clap(long)]
+    deferred: bool,
+    #[clap(long, arg_enum, default_value = "depth32-float")]
+    depth_format: DepthFormat,
+    #[clap(long, default_value_t = 1.0)]
+    displace_scale: f32,
+    #[clap(long)]
+    dolly_zoom: bool,
+    #[clap(long)]
+    draw_limit: Option<u32>,
+    #[clap(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    exposure: f32,
+    #[clap(long, default_value_t = 1.7)]
+    eye_height: f32,
+    #[clap(long)]
+    fake_ao: Option<Option<f32>>,
+    #[clap(long)]
+    fence: bool,
+    #[clap(long, default_value_t = 2.0)]
+    fence_spacing: f32,
     #[clap(short, long)]
     file: bool,
+    #[clap(long)]
+    flip_normals: bool,
+    #[clap(long)]
+    flip_winding: bool,
+    #[clap(long, parse(try_from_str = color::parse_hex))]
+    fog_color: Option<[f32; 3]>,
+    #[clap(long, default_value_t = 0.0)]
+    fog_density: f32,
+    #[clap(long, default_value_t = 0.0)]
+    fog_end: f32,
+    #[clap(long, default_value_t = 0.0)]
+    fog_start: f32,
+    #[clap(long)]
+    gpu_debug: bool,
+    #[clap(long, arg_enum)]
+    grid: Option<Grid>,
+    #[clap(long)]
+    headlight: bool,
     #[clap(long, default_value_t = 1.0)]
     height: f32,
+    #[clap(long, parse(from_os_str))]
+    heightmap: Option<PathBuf>,
     #[clap(short, long)]
     house: bool,
+    #[clap(long, parse(from_os_str))]
+    info: Option<PathBuf>,
+    #[clap(long, default_value_t = 0.0)]
+    instance_angle: f32,
+    #[clap(long, arg_enum, default_value = "y")]
+    instance_axis: Axis,
+    #[clap(long, default_value_t = 1)]
+    instance_count: u32,
+    #[clap(long)]
+    invert_x: bool,
+    #[clap(long)]
+    invert_y: bool,
+    #[clap(long, default_value_t = 45.0)]
+    latitude: f32,
+    #[clap(long, arg_enum, default_value = "grid")]
+    layout: Layout,
     #[clap(long, default_value_t = 1.0)]
     length: f32,
+    #[clap(long, parse(try_from_str = color::parse_hex))]
+    light_color: Option<[f32; 3]>,
+    #[clap(long)]
+    light_height: Option<f32>,
+    #[clap(long, default_value_t = 1.0)]
+    light_intensity: f32,
+    #[clap(long)]
+    light_orbit_radius: Option<f32>,
+    #[clap(long, default_value_t = 60.0)]
+    light_orbit_speed: f32,
+    #[clap(long, default_value_t = 0.25)]
+    light_step: f32,
+    #[clap(long)]
+    list_primitives: bool,
+    #[clap(long)]
+    load_threads: Option<usize>,
+    #[clap(long)]
+    loop_frames: Option<u32>,
     #[clap(long, default_value_t = 0.5)]
     max: f32,
+    #[clap(long, default_value_t = 100)]
+    max_dt_ms: u64,
+    #[clap(long, parse(from_os_str), multiple_values = true)]
+    merge: Vec<PathBuf>,
+    #[clap(long, parse(try_from_str = color::parse_indexed_hex))]
+    mesh_color: Vec<(usize, [f32; 3])>,
+    #[clap(long, parse(try_from_str = color::parse_indexed_hex))]
+    mesh_emissive: Vec<(usize, [f32; 3])>,
+    #[clap(long, default_value_t = 0.0)]
+    metallic: f32,
+    #[clap(long)]
+    no_clear_color: bool,
+    #[clap(long)]
+    no_clear_depth: bool,
+    #[clap(long)]
+    no_cull: bool,
+    #[clap(long)]
+    no_default_model: bool,
+    #[clap(long)]
+    no_depth: bool,
+    #[clap(long)]
+    no_rails: bool,
+    #[clap(long)]
+    no_triangulate: bool,
+    #[clap(long, default_value_t = 0.1)]
+    normal_length: f32,
+    #[clap(long)]
+    normalize: bool,
+    #[clap(long, default_value_t = 1.0)]
+    normalize_to: f32,
+    #[clap(long, default_value_t = 15.0)]
+    orbit_step: f32,
     #[clap(short, long)]
     plane: bool,
+    #[clap(long, default_value_t = 1)]
+    plane_subdivisions: u32,
+    #[clap(long, default_value_t = 4.0)]
+    point_size: f32,
+    #[clap(long)]
+    points: bool,
+    #[clap(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    #[clap(long)]
+    recenter: bool,
+    #[clap(long, default_value_t = 120)]
+    record_count: u32,
+    #[clap(long, parse(from_os_str))]
+    record_frames: Option<PathBuf>,
+    #[clap(long)]
+    repair: bool,
+    #[clap(long, env = "RR_RES_DIR", parse(from_os_str))]
+    res_dir: Option<PathBuf>,
+    #[clap(long, default_value_t = 0.5)]
+    roughness: f32,
+    #[clap(long)]
+    rows: Option<u32>,
+    #[clap(long)]
+    scatter: Option<u32>,
+    #[clap(long, default_value_t = 10.0)]
+    scatter_radius: f32,
+    #[clap(long)]
+    scatter_rotate: bool,
+    #[clap(long, default_value_t = 0.0, parse(try_from_str = instance::parse_scatter_scale))]
+    scatter_scale: f32,
+    #[clap(long, parse(from_os_str))]
+    scene: Option<PathBuf>,
+    #[clap(long, parse(from_os_str), default_value = ".")]
+    screenshot_dir: PathBuf,
+    #[clap(long)]
+    seed: Option<u64>,
+    #[clap(long, arg_enum, default_value = "phong")]
+    shading: ShadingModel,
+    #[clap(long, default_value_t = 32.0)]
+    shininess: f32,
     #[clap(long, default_value_t = 1.0)]
     size: f32,
+    #[clap(long, default_value_t = 0)]
+    smooth_iterations: u32,
+    #[clap(long, default_value_t = 1.0)]
+    spacing: f32,
+    #[clap(long, default_value_t = 0.5)]
+    specular_strength: f32,
+    #[clap(long, arg_enum)]
+    split: Option<ShadingModel>,
+    #[clap(long, parse(from_os_str))]
+    stats_json: Option<PathBuf>,
+    #[clap(long)]
+    strict: bool,
     #[clap(short, long)]
     surface: bool,
+    #[clap(long)]
+    surface_quads: bool,
+    #[clap(long)]
+    surface_x_size: Option<f32>,
+    #[clap(long)]
+    surface_z_size: Option<f32>,
+    #[clap(long, default_value_t = 256)]
+    thumb_size: u32,
+    #[clap(long, parse(from_os_str), number_of_values = 2, value_names = &["IN", "OUT"])]
+    thumbnail: Option<Vec<PathBuf>>,
+    #[clap(long, parse(try_from_str = light::parse_time_of_day))]
+    time_of_day: Option<f32>,
+    #[clap(long)]
+    transparent: bool,
+    #[clap(long)]
+    ui: bool,
+    #[clap(long, arg_enum, default_value = "y")]
+    up_axis: UpAxis,
+    #[clap(long)]
+    validate: bool,
+    #[clap(short, long, parse(from_occurrences))]
+    verbose: u64,
+    #[clap(long)]
+    walk: bool,
     #[clap(long, default_value_t = 1.0)]
     width: f32,
+    #[clap(long)]
+    wire_width: Option<f32>,
+    #[clap(long)]
+    wireframe: bool,
+    #[clap(long, default_value_t = 0.0)]
+    wrap: f32,
+    #[clap(long)]
+    xray: bool,
+    #[clap(long, arg_enum, default_value = "distance")]
+    zoom_mode: ZoomMode,
+}
+
+// -q and -v override RUST_LOG, which otherwise decides the level as before
+fn init_logging(quiet: bool, verbose: u64) {
+    let mut builder = env_logger::Builder::from_default_env();
+    let level = match (quiet, verbose) {
+        (true, _) => Some(log::LevelFilter::Off),
+        (false, 0) => None,
+        (false, 1) => Some(log::LevelFilter::Info),
+        (false, 2) => Some(log::LevelFilter::Debug),
+        _ => Some(log::LevelFilter::Trace),
+    };
+
+    if let Some(level) = level {
+        builder.filter_level(level);
+    }
+    builder.init();
 }
 
 fn main() {
-    env_logger::init();
     let cli = Cli::parse();
+    init_logging(cli.quiet, cli.verbose);
+
+    if cli.validate {
+        let valid = pollster::block_on(State::validate()).unwrap_or_else(|error| {
+            eprintln!("{:?}", error);
+            false
+        });
+
+        std::process::exit(if valid { 0 } else { 1 });
+    }
+
+    if cli.list_primitives {
+        for primitive in model::PRIMITIVES {
+            println!("{} ({}): {}", primitive.name, primitive.flag, primitive.parameters.join(", "));
+        }
+        std::process::exit(0);
+    }
+
+    let import_options = ImportOptions {
+        color_source: cli.color_source,
+        flip_normals: cli.flip_normals,
+        flip_winding: cli.flip_winding,
+        keep_polygons: cli.no_triangulate,
+        load_pool: cli.load_threads.and_then(|threads| {
+            match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+                Ok(pool) => Some(Arc::new(pool)),
+                Err(error) => {
+                    log::warn!("Couldn't start {} load threads, loading on the global pool: {}", threads, error);
+                    None
+                }
+            }
+        }),
+        normalize: cli.normalize.then(|| cli.normalize_to),
+        recenter: cli.recenter,
+        repair: cli.repair,
+        smooth_iterations: cli.smooth_iterations,
+        strict: cli.strict,
+        up_axis: cli.up_axis,
+    };
+
+    if let Some(paths) = &cli.thumbnail {
+        let result = pollster::block_on(State::thumbnail(&paths[0], &paths[1], cli.thumb_size, &import_options));
+
+        if let Err(error) = &result {
+            eprintln!("{:?}", error);
+        }
+        std::process::exit(if result.is_ok() { 0 } else { 1 });
+    }
+    if let Some(path) = &cli.info {
+        let result = pollster::block_on(State::info(path, &import_options));
+
+        if let Err(error) = &result {
+            eprintln!("{:?}", error);
+        }
+        std::process::exit(if result.is_ok() { 0 } else { 1 });
+    }
+
+    // The deferred lighting pass only has the Phong model and draws the whole frame with it
+    if cli.deferred && (cli.shading == ShadingModel::Pbr || cli.split.is_some()) {
+        log::warn!("--deferred always shades with Phong, --shading pbr and --split are ignored");
+    }
+
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
-    let mut state = pollster::block_on(State::new(&window));
+    let window = WindowBuilder::new()
+        .with_transparent(cli.transparent)
+        .build(&event_loop)
+        .unwrap();
+    let render_options = RenderOptions {
+        deferred: cli.deferred,
+        depth_format: (!cli.no_depth).then(|| cli.depth_format.into()),
+    };
+    let mut state = pollster::block_on(State::new(&window, render_options));
 
+    if !cli.no_default_model {
+        if let Err(error) = state.load_default_model(cli.res_dir.as_deref()) {
+            log::warn!("Couldn't load the default model, using a plain cube: {:?}", error);
+        }
+    }
+    state.set_accumulate(cli.accumulate);
+    state.set_aspect_lock(cli.aspect);
+    state.set_camera(vec3(&cli.camera_pos), vec3(&cli.camera_target));
+    state.set_checker_background(cli.checker_bg);
+    state.set_clear(!cli.no_clear_color, !cli.no_clear_depth);
+    state.set_clip_plane(cli.clip_plane.as_deref().map(|plane| [plane[0], plane[1], plane[2], plane[3]]));
+    state.set_cull_backfaces(!cli.no_cull);
+    state.set_dolly_zoom(cli.dolly_zoom);
+    state.set_draw_limit(cli.draw_limit);
+    state.set_exposure(cli.exposure);
+    state.set_fake_ao(cli.fake_ao.map_or(0.0, |strength| strength.unwrap_or(0.5)));
+    state.set_fog(cli.fog_color, cli.fog_density, cli.fog_start, cli.fog_end);
+    state.set_gpu_debug(cli.gpu_debug);
+    state.set_grid(cli.grid);
+    state.set_headlight(cli.headlight);
+    state.set_import_options(import_options);
+    state.set_light_orbit(
+        cli.light_orbit_radius,
+        cli.light_height,
+        cli.light_orbit_speed,
+        cli.animate_light_path,
+    );
+    state.set_light_step(cli.light_step);
+    state.set_loop_frames(cli.loop_frames);
+    if let Some(time_of_day) = cli.time_of_day {
+        state.set_sun(time_of_day, cli.latitude);
+    }
+    // After the sun, so an explicit color replaces its color
+    state.set_light_color(cli.light_color, cli.light_intensity);
+    state.set_max_dt(std::time::Duration::from_millis(cli.max_dt_ms));
+    state.set_material(cli.shading, cli.metallic, cli.roughness);
+    state.set_mouse_inversion(cli.invert_x, cli.invert_y);
+    state.set_normal_length(cli.normal_length);
+    state.set_orbit_step(cli.orbit_step);
+    state.set_point_mode(cli.points, cli.point_size);
+    state.set_screenshot_dir(cli.screenshot_dir.clone());
+    state.set_specular(cli.shininess, cli.specular_strength);
+    state.set_split(cli.split);
+    state.set_transparent(cli.transparent);
+    state.set_walk(cli.walk.then(|| cli.eye_height.max(0.0)));
+    state.set_wire_width(cli.wire_width);
+    state.set_wireframe(cli.wireframe);
+    state.set_wrap(cli.wrap);
+    state.set_xray(cli.xray);
+    state.set_zoom_mode(cli.zoom_mode);
+    if cli.ui {
+        state.enable_ui(&window);
+    }
     state.render().unwrap();
 
+    let mut instances = match cli.scatter {
+        Some(count) => {
+            let seed = cli.seed.unwrap_or_else(rand::random);
+
+            log::info!("Scattering {} instances with seed {}", count, seed);
+            instance::scatter(count, cli.scatter_radius, seed, cli.scatter_rotate, cli.scatter_scale)
+        }
+        None => instance::layout(cli.layout, cli.instance_count, cli.spacing),
+    };
+
+    instance::orient(&mut instances, cli.instance_axis, Deg(cli.instance_angle));
+    state.set_instances(instances);
+
     if cli.cube {
         state.add_model_primitive(ModelPrimitive::Cube, cli.size);
     }
+    if cli.fence {
+        state.add_fence(cli.count, cli.fence_spacing, cli.width, cli.length, cli.height, !cli.no_rails);
+    }
     if cli.file {
         state.prompt_for_file().unwrap();
     }
+    if let Some(path) = &cli.heightmap {
+        if let Err(error) = state.add_heightmap(path, cli.size, cli.plane_subdivisions, cli.displace_scale) {
+            eprintln!("{:?}", error);
+            std::process::exit(1);
+        }
+    }
     if cli.house {
         state.add_house(cli.width, cli.length, cli.height);
     }
+    if !cli.merge.is_empty() {
+        if let Err(error) = state.add_merged(&cli.merge) {
+            eprintln!("{:?}", error);
+            std::process::exit(1);
+        }
+    }
     if cli.plane {
-        state.add_model_primitive(ModelPrimitive::Plane, cli.size);
+        state.add_model_primitive(ModelPrimitive::Plane { subdivisions: cli.plane_subdivisions }, cli.size);
     }
     if cli.surface {
-        state.add_surface(cli.count, cli.size, cli.max);
+        state.add_surface(
+            [cli.cols.unwrap_or(cli.count), cli.rows.unwrap_or(cli.count)],
+            [cli.surface_x_size.unwrap_or(cli.size), cli.surface_z_size.unwrap_or(cli.size)],
+            cli.max,
+            cli.surface_quads,
+        );
+    }
+    if let Some(path) = &cli.scene {
+        if let Err(error) = Scene::load(path).and_then(|scene| state.load_scene(&scene)) {
+            eprintln!("{:?}", error);
+            std::process::exit(1);
+        }
+    }
+    state.set_mesh_colors(&cli.mesh_color);
+    state.set_mesh_emissive(&cli.mesh_emissive);
+    if cli.center_light_on_model {
+        state.center_light_on_models();
+    }
+
+    if let Some(path) = &cli.camera_path {
+        match CameraPath::load(path) {
+            Ok(camera_path) => state.set_camera_path(camera_path),
+            Err(error) => {
+                eprintln!("{:?}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(dir) = cli.record_frames.clone() {
+        // A loop records exactly one turn
+        state.start_recording(dir, cli.loop_frames.unwrap_or(cli.record_count)).unwrap();
+    }
+    if let Some(path) = cli.stats_json.clone() {
+        state.start_stats(path);
     }
 
     let mut last_render_time = std::time::Instant::now();
@@ -94,7 +522,7 @@ fn main() {
             Event::WindowEvent {
                 ref event,
                 window_id,
-            } if window_id == window.id() => {
+            } if window_id == window.id() && !state.ui_event(event) => {
                 match event {
                     WindowEvent::CloseRequested
                     | WindowEvent::KeyboardInput {
@@ -106,11 +534,20 @@ fn main() {
                             },
                         ..
                     } => *control_flow = ControlFlow::Exit,
+                    WindowEvent::CursorMoved { position, .. } => {
+                        state.cursor_moved(position.x, position.y);
+                    }
+                    WindowEvent::ModifiersChanged(modifiers) => {
+                        state.modifiers_changed(*modifiers);
+                    }
+                    // Redrawn straight away so the new surface isn't shown before anything is drawn to it
                     WindowEvent::Resized(physical_size) => {
                         state.resize(*physical_size);
+                        window.request_redraw();
                     }
                     WindowEvent::ScaleFactorChanged { new_inner_size, ..} => {
                         state.resize(**new_inner_size);
+                        window.request_redraw();
                     }
                     _ => {}
                 }
@@ -121,19 +558,35 @@ fn main() {
 
                 last_render_time = now;
                 state.update(dt);
+                state.update_ui(&window);
                 match state.render() {
                     Ok(_) => {}
                     Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
                     Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
                     Err(e) => eprintln!("{:?}", e),
                 }
+                if let Err(error) = state.record_frame() {
+                    eprintln!("{:?}", error);
+                    *control_flow = ControlFlow::Exit;
+                }
+                if state.recording_finished() || state.camera_path_finished() || state.turntable_finished() {
+                    *control_flow = ControlFlow::Exit;
+                }
             }
             Event::MainEventsCleared => {
                 window.request_redraw();
             }
+            Event::LoopDestroyed => {
+                if let Err(error) = state.write_stats() {
+                    eprintln!("Failed to write stats: {:?}", error);
+                }
+            }
             _ => {}
         }
     });
 }
 
-
+// clap has already checked there are exactly three values
+fn vec3(values: &[f32]) -> [f32; 3] {
+    [values[0], values[1], values[2]]
+}
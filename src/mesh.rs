@@ -1,35 +1,254 @@
-use cgmath::{InnerSpace, Vector3};
+use cgmath::{InnerSpace, Matrix4, Quaternion, SquareMatrix, Vector3};
+use rand::Rng;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::collections::HashMap;
 use wgpu::util::DeviceExt;
 
+use crate::{
+  bounds::Aabb,
+  uniform::Uniform,
+};
+
 const COLOR: [f32;4] = [1.0, 0.1, 0.1, 1.0];
 
+// Where the albedo comes from when a mesh has both vertex colors and a material color
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ColorSource {
+  Material,
+  Multiply,
+  Vertex,
+}
+
+impl ColorSource {
+  // Matches color_source in the Mesh struct of mesh.wgsl
+  fn shader_index(self) -> u32 {
+    match self {
+      ColorSource::Vertex => 0,
+      ColorSource::Material => 1,
+      ColorSource::Multiply => 2,
+    }
+  }
+}
+
 pub trait Vertex {
   fn desc<'a>() -> wgpu::VertexBufferLayout<'a>;
 }
 
 pub struct Mesh {
+  pub bounds: Aabb,
   pub name: String,
   pub vertex_buffer: wgpu::Buffer,
   pub index_buffer: wgpu::Buffer,
+  pub index_format: wgpu::IndexFormat,
   pub num_elements: u32,
+  pub num_vertices: u32,
   pub material: usize,
+  pub topology: wgpu::PrimitiveTopology,
+  pub uniform: Uniform<MeshUniform>,
+  // CPU copy for overlays built after load, like the normals
+  pub vertices: Vec<MeshVertex>,
+  pub visible: bool,
+  // Line overlays drawn with this mesh's placement, its polygon outlines and vertex normals
+  pub edges: Option<Box<Mesh>>,
+  pub normals: Option<Box<Mesh>>,
+}
+
+impl Mesh {
+  pub fn set_color(&mut self, queue: &wgpu::Queue, color: [f32; 3]) {
+    let [r, g, b] = color;
+
+    self.uniform.uniform.color = [r, g, b, 1.0];
+    // Still multiplied with the vertex colors if that was asked for
+    if self.uniform.uniform.color_source != ColorSource::Multiply.shader_index() {
+      self.uniform.uniform.color_source = ColorSource::Material.shader_index();
+    }
+    self.write_uniform(queue);
+  }
+
+  // Added to the shaded color whatever the lighting, so the mesh glows
+  pub fn set_emissive(&mut self, queue: &wgpu::Queue, emissive: [f32; 3]) {
+    self.uniform.uniform.emissive = emissive;
+    self.write_uniform(queue);
+  }
+
+  pub fn set_offset(&mut self, queue: &wgpu::Queue, offset: Vector3<f32>) {
+    self.uniform.uniform.offset = offset.into();
+    self.write_uniform(queue);
+  }
+
+  // Places the mesh within its model, applied before the explode offset and the instance transform
+  pub fn set_transform(&mut self, queue: &wgpu::Queue, transform: Matrix4<f32>) {
+    self.bounds = Aabb::from_points(
+      self.vertices.iter().map(|vertex| (transform * Vector3::from(vertex.position).extend(1.0)).truncate()),
+    );
+    self.uniform.uniform.transform = transform.into();
+    self.write_uniform(queue);
+  }
+
+  // The overlays get the same placement, the line shaders read nothing else from it
+  fn write_uniform(&self, queue: &wgpu::Queue) {
+    let uniform = [self.uniform.uniform];
+
+    for mesh in std::iter::once(self).chain(self.edges.as_deref()).chain(self.normals.as_deref()) {
+      queue.write_buffer(&mesh.uniform.buffer, 0, bytemuck::cast_slice(&uniform));
+    }
+  }
+}
+
+// Per mesh shading overrides and placement, bound at group 3 of the model pipelines
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MeshUniform {
+  pub color: [f32; 4],
+  // Model space translation, used to pull meshes apart in the exploded view
+  pub offset: [f32; 3],
+  pub color_source: u32,
+  // Normals go through its upper 3x3, so it should keep to rotation, translation and uniform scale
+  pub transform: [[f32; 4]; 4],
+  pub emissive: [f32; 3],
+  pub _padding: u32,
+}
+
+impl Default for MeshUniform {
+  fn default() -> Self {
+    Self {
+      color: [0.0; 4],
+      offset: [0.0; 3],
+      color_source: 0,
+      transform: Matrix4::identity().into(),
+      emissive: [0.0; 3],
+      _padding: 0,
+    }
+  }
+}
+
+// Geometry that breaks lighting and picking without any error of its own
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GeometryReport {
+  pub degenerate_triangles: usize,
+  pub non_finite_normals: usize,
+  pub non_finite_positions: usize,
+}
+
+impl GeometryReport {
+  pub fn combine(self, other: Self) -> Self {
+    Self {
+      degenerate_triangles: self.degenerate_triangles + other.degenerate_triangles,
+      non_finite_normals: self.non_finite_normals + other.non_finite_normals,
+      non_finite_positions: self.non_finite_positions + other.non_finite_positions,
+    }
+  }
+
+  pub fn is_clean(&self) -> bool {
+    self.degenerate_triangles == 0 && self.non_finite_normals == 0 && self.non_finite_positions == 0
+  }
 }
 
 pub struct MeshBuilder {
+  color_source: Option<ColorSource>,
+  emissive: [f32; 3],
   indices: Vec<u32>,
   label: String,
+  material: usize,
+  material_color: Option<[f32; 3]>,
+  topology: wgpu::PrimitiveTopology,
   vertices: Vec<MeshVertex>,
 }
 
 impl MeshBuilder {
   pub fn new(label: &str) -> Self {
     Self {
+      color_source: None,
+      emissive: [0.0; 3],
       indices: Vec::new(),
       label: String::from(label),
+      material: 0,
+      material_color: None,
+      topology: wgpu::PrimitiveTopology::TriangleList,
       vertices: Vec::new(),
     }
   }
 
+  // A cube of edge size centered on the origin, each face wound to face outwards
+  pub fn cube(size: f32) -> Self {
+    let mut builder = Self::new("Cube");
+    let up = size * Vector3::unit_y();
+    let right = size * Vector3::unit_x();
+    let forward = size * Vector3::unit_z();
+    let near_corner = Vector3::new(-size / 2.0, -size / 2.0, -size / 2.0);
+    let far_corner = Vector3::new(size / 2.0, size / 2.0, size / 2.0);
+
+    builder.add_quad(near_corner, forward, right);
+    builder.add_quad(near_corner, right, up);
+    builder.add_quad(near_corner, up, forward);
+
+    builder.add_quad(far_corner, -right, -forward);
+    builder.add_quad(far_corner, -up, -right);
+    builder.add_quad(far_corner, -forward, -up);
+
+    builder
+  }
+
+  pub fn from_geometry(label: &str, vertices: Vec<MeshVertex>, indices: Vec<u32>) -> Self {
+    Self {
+      indices,
+      vertices,
+      ..Self::new(label)
+    }
+  }
+
+  // A grid of linked quads centered on the origin with random heights up to height_max. counts and
+  // sizes are [x, z], the columns and rows of the grid and the spacing along each.
+  pub fn surface(counts: [u32; 2], sizes: [f32; 2], height_max: f32) -> Self {
+    let mut builder = Self::new("Quad Grid");
+    let [half_cols, half_rows] = counts.map(|count| count as i32 / 2);
+    let [x_size, z_size] = sizes;
+    let row_length = Self::surface_row_length(counts);
+    let cells = (-half_rows..half_rows + 1)
+      .flat_map(|i| (-half_cols..half_cols + 1).map(move |j| (i, j)))
+      .collect::<Vec<_>>();
+    // Heights are generated in parallel, the indexed mesh is linked up serially
+    let positions = cells.par_iter().map_init(rand::thread_rng, |rng, &(i, j)| {
+      let x = 2.0 * x_size * j as f32;
+      // An empty range panics, a flat surface has none to pick from
+      let y = if height_max > 0.0 { rng.gen_range(0.0..height_max) } else { 0.0 };
+      let z = 2.0 * z_size * i as f32;
+
+      Vector3::new(x, y, z)
+    }).collect::<Vec<_>>();
+
+    for (&(i, j), position) in cells.iter().zip(positions) {
+      let link = i > -half_rows && j > -half_cols;
+
+      builder.add_linked_quad(position, link, row_length);
+    }
+
+    builder
+  }
+
+  // Odd counts round down, so a row holds 2 * (cols / 2) + 1 vertices rather than cols + 1
+  pub fn surface_row_length(counts: [u32; 2]) -> u32 {
+    2 * (counts[0] / 2) + 1
+  }
+
+  pub fn add_colored_vertex<P, N>(&mut self, position: P, normal: N, color: [f32; 4])
+  where
+    P: Into<[f32;3]>,
+    N: Into<[f32;3]>,
+  {
+    self.vertices.push(MeshVertex {
+      position: position.into(),
+      normal: normal.into(),
+      color,
+    });
+  }
+
+  pub fn add_edge(&mut self, indices: (u32, u32)) {
+    let (i1, i2) = indices;
+    self.indices.push(i1);
+    self.indices.push(i2);
+  }
+
   pub fn add_face(&mut self, indices: (u32, u32, u32)) {
     let (i1, i2, i3) = indices;
     self.indices.push(i1);
@@ -37,6 +256,8 @@ impl MeshBuilder {
     self.indices.push(i3);
   }
 
+  // Adds a grid vertex and, when linked, the quad back to the previous vertex and the two a row
+  // earlier. index_offset is the row length.
   pub fn add_linked_quad(&mut self, position: Vector3<f32>, link: bool, index_offset: u32) {
     self.add_vertex(position, Vector3::unit_y());
 
@@ -51,6 +272,7 @@ impl MeshBuilder {
     }
   }
 
+  // Faces along length x width, wound counter-clockwise when seen from that side
   pub fn add_quad(&mut self, position: Vector3<f32>, width: Vector3<f32>, length: Vector3<f32>) {
     let normal = length.cross(width).normalize();
 
@@ -82,35 +304,265 @@ impl MeshBuilder {
     P: Into<[f32;3]>,
     N: Into<[f32;3]>,
   {
-    self.vertices.push(MeshVertex {
-      position: position.into(),
-      normal: normal.into(),
-      color: COLOR,
-    });
+    self.add_colored_vertex(position, normal, COLOR);
+  }
+
+  // Adds another builder's geometry, keeping this builder's label, material and topology
+  pub fn append(&mut self, other: &MeshBuilder) {
+    let offset = self.vertex_count();
+
+    self.vertices.extend_from_slice(&other.vertices);
+    self.indices.extend(other.indices.iter().map(|&i| i + offset));
+  }
+
+  pub fn bounds(&self) -> Aabb {
+    Aabb::from_points(self.vertices.iter().map(|vertex| Vector3::from(vertex.position)))
+  }
+
+  // Replaces vertex normals with the area weighted average of the adjacent face normals
+  pub fn compute_normals(&mut self) {
+    let mut normals = vec![Vector3::new(0.0, 0.0, 0.0); self.vertices.len()];
+
+    for face in self.indices.chunks_exact(3) {
+      let [p0, p1, p2] = [face[0], face[1], face[2]]
+        .map(|i| Vector3::from(self.vertices[i as usize].position));
+      let normal = (p1 - p0).cross(p2 - p0);
+
+      for &i in face {
+        normals[i as usize] += normal;
+      }
+    }
+
+    for (vertex, normal) in self.vertices.iter_mut().zip(normals) {
+      vertex.normal = if normal.magnitude2() > 0.0 {
+        normal.normalize().into()
+      } else {
+        Vector3::unit_y().into()
+      };
+    }
+  }
+
+  pub fn flip_normals(&mut self) {
+    for vertex in &mut self.vertices {
+      vertex.normal = vertex.normal.map(|n| -n);
+    }
+  }
+
+  // Reverses the vertex order of every triangle, turning front faces into back faces
+  pub fn flip_winding(&mut self) {
+    for face in self.indices.chunks_exact_mut(3) {
+      face.swap(1, 2);
+    }
+  }
+
+  // Drops zero area triangles from the index buffer, returning how many went
+  pub fn remove_degenerate_faces(&mut self) -> usize {
+    if self.topology != wgpu::PrimitiveTopology::TriangleList {
+      return 0;
+    }
+
+    let faces = self.indices.len() / 3;
+    let indices = self.indices.chunks_exact(3)
+      .filter(|face| !self.is_degenerate(face))
+      .flatten()
+      .copied()
+      .collect::<Vec<_>>();
+
+    self.indices = indices;
+
+    faces - self.indices.len() / 3
+  }
+
+  // Turns positions and normals about the origin
+  pub fn rotate(&mut self, rotation: Quaternion<f32>) {
+    for vertex in &mut self.vertices {
+      vertex.position = (rotation * Vector3::from(vertex.position)).into();
+      vertex.normal = (rotation * Vector3::from(vertex.normal)).into();
+    }
+  }
+
+  // Laplacian smoothing, each pass moves every vertex to the average of its neighbours. Vertices
+  // split at a seam share a position and move together so the seam stays closed.
+  pub fn smooth(&mut self, iterations: u32) {
+    let mut welded = HashMap::new();
+    let groups = self.vertices.iter().map(|vertex| {
+      let next = welded.len();
+
+      *welded.entry(vertex.position.map(f32::to_bits)).or_insert(next)
+    }).collect::<Vec<usize>>();
+    let mut neighbours = vec![Vec::new(); welded.len()];
+
+    for face in self.indices.chunks_exact(3) {
+      for (a, b) in [(face[0], face[1]), (face[1], face[2]), (face[2], face[0])] {
+        let (a, b) = (groups[a as usize], groups[b as usize]);
+
+        if a != b {
+          neighbours[a].push(b);
+          neighbours[b].push(a);
+        }
+      }
+    }
+    for around in &mut neighbours {
+      around.sort_unstable();
+      around.dedup();
+    }
+
+    let mut positions = vec![Vector3::new(0.0, 0.0, 0.0); welded.len()];
+
+    for (vertex, &group) in self.vertices.iter().zip(&groups) {
+      positions[group] = Vector3::from(vertex.position);
+    }
+    for _ in 0..iterations {
+      positions = neighbours.iter().zip(&positions).map(|(around, &position)| {
+        if around.is_empty() {
+          return position;
+        }
+
+        around.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, &i| sum + positions[i]) / around.len() as f32
+      }).collect();
+    }
+    for (vertex, &group) in self.vertices.iter_mut().zip(&groups) {
+      vertex.position = positions[group].into();
+    }
+  }
+
+  // None picks the material color when there is one, otherwise the vertex colors
+  pub fn set_color_source(&mut self, color_source: Option<ColorSource>) {
+    self.color_source = color_source;
+  }
+
+  pub fn set_emissive(&mut self, emissive: [f32; 3]) {
+    self.emissive = emissive;
+  }
+
+  pub fn set_material(&mut self, material: usize) {
+    self.material = material;
+  }
+
+  pub fn set_material_color(&mut self, color: [f32; 3]) {
+    self.material_color = Some(color);
+  }
+
+  // Moves origin to zero then scales about it, so the shape and normals are kept
+  pub fn transform_positions(&mut self, origin: Vector3<f32>, scale: f32) {
+    for vertex in &mut self.vertices {
+      vertex.position = ((Vector3::from(vertex.position) - origin) * scale).into();
+    }
+  }
+
+  // Gives every index its own copy of its vertex, so index i is vertex i
+  pub fn unweld(&mut self) {
+    self.vertices = self.indices.iter().map(|&i| self.vertices[i as usize]).collect();
+    self.indices = (0..self.vertices.len() as u32).collect();
+  }
+
+  pub fn validate(&self) -> GeometryReport {
+    let finite = |values: &[f32; 3]| values.iter().all(|value| value.is_finite());
+    let degenerate_triangles = if self.topology == wgpu::PrimitiveTopology::TriangleList {
+      self.indices.chunks_exact(3).filter(|face| self.is_degenerate(face)).count()
+    } else {
+      0
+    };
+
+    GeometryReport {
+      degenerate_triangles,
+      non_finite_normals: self.vertices.iter().filter(|vertex| !finite(&vertex.normal)).count(),
+      non_finite_positions: self.vertices.iter().filter(|vertex| !finite(&vertex.position)).count(),
+    }
+  }
+
+  fn is_degenerate(&self, face: &[u32]) -> bool {
+    let [p0, p1, p2] = [face[0], face[1], face[2]]
+      .map(|i| Vector3::from(self.vertices[i as usize].position));
+
+    (p1 - p0).cross(p2 - p0).magnitude2() == 0.0
+  }
+
+  pub fn vertices(&self) -> &[MeshVertex] {
+    &self.vertices
+  }
+
+  pub fn vertex_count(&self) -> u32 {
+    self.vertices.len() as u32
+  }
+
+  pub fn topology(&self) -> wgpu::PrimitiveTopology {
+    self.topology
+  }
+
+  pub fn set_topology(&mut self, topology: wgpu::PrimitiveTopology) {
+    self.topology = topology;
+  }
+
+  // Without a material color there is nothing to use but the vertex colors
+  fn uniform(&self) -> MeshUniform {
+    match self.material_color {
+      Some([r, g, b]) => MeshUniform {
+        color: [r, g, b, 1.0],
+        color_source: self.color_source.unwrap_or(ColorSource::Material).shader_index(),
+        emissive: self.emissive,
+        ..MeshUniform::default()
+      },
+      None => MeshUniform {
+        emissive: self.emissive,
+        ..MeshUniform::default()
+      },
+    }
   }
 
   pub fn build(&self, device: &wgpu::Device) -> Mesh {
+    self.build_with_usage(device, wgpu::BufferUsages::VERTEX, self.uniform())
+  }
+
+  // Starts out placed like source, whose write_uniform keeps it that way once attached to it
+  pub fn build_overlay(&self, device: &wgpu::Device, source: &Mesh) -> Mesh {
+    self.build_with_usage(device, wgpu::BufferUsages::VERTEX, source.uniform.uniform)
+  }
+
+  // For meshes whose vertices are rewritten in place with queue.write_buffer, keeping their count
+  pub fn build_writable(&self, device: &wgpu::Device) -> Mesh {
+    self.build_with_usage(device, wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST, self.uniform())
+  }
+
+  fn build_with_usage(&self, device: &wgpu::Device, usage: wgpu::BufferUsages, uniform: MeshUniform) -> Mesh {
     let vertex_buffer = device.create_buffer_init(
       &wgpu::util::BufferInitDescriptor {
         label: Some(&format!("{} Vertex Buffer", self.label)),
         contents: bytemuck::cast_slice(&self.vertices),
-        usage: wgpu::BufferUsages::VERTEX,
+        usage,
       }
     );
+    // Meshes whose indices all fit in 16 bits store them that way, halving the buffer
+    let (index_format, index_bytes) = if self.vertices.len() <= u16::MAX as usize {
+      let indices = self.indices.iter().map(|&i| i as u16).collect::<Vec<_>>();
+
+      (wgpu::IndexFormat::Uint16, bytemuck::cast_slice(&indices).to_vec())
+    } else {
+      (wgpu::IndexFormat::Uint32, bytemuck::cast_slice(&self.indices).to_vec())
+    };
     let index_buffer = device.create_buffer_init(
       &wgpu::util::BufferInitDescriptor {
         label: Some(&format!("{} Index Buffer", self.label)),
-        contents: bytemuck::cast_slice(&self.indices),
+        contents: &index_bytes,
         usage: wgpu::BufferUsages::INDEX,
       }
     );
 
     Mesh {
+      bounds: self.bounds(),
       name: String::from(&self.label),
       vertex_buffer,
       index_buffer,
+      index_format,
       num_elements: self.indices.len() as u32,
-      material: 0,
+      num_vertices: self.vertices.len() as u32,
+      material: self.material,
+      topology: self.topology,
+      uniform: Uniform::new(device, uniform, &format!("{} mesh", self.label)),
+      vertices: self.vertices.clone(),
+      visible: true,
+      edges: None,
+      normals: None,
     }
   }
 }
@@ -150,3 +602,108 @@ impl Vertex for MeshVertex {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    instance::{self, InstanceBuffer},
+    texture::Texture,
+  };
+  use std::collections::HashSet;
+
+  #[test]
+  fn cube_faces_point_outwards() {
+    let builder = MeshBuilder::cube(2.0);
+
+    for face in builder.indices.chunks_exact(3) {
+      let [p0, p1, p2] = [face[0], face[1], face[2]]
+        .map(|i| Vector3::from(builder.vertices[i as usize].position));
+      let winding_normal = (p1 - p0).cross(p2 - p0);
+      let centroid = (p0 + p1 + p2) / 3.0;
+
+      assert!(winding_normal.dot(centroid) > 0.0, "face {:?} is wound inwards", face);
+      for &i in face {
+        let normal = Vector3::from(builder.vertices[i as usize].normal);
+
+        assert!(normal.dot(centroid) > 0.0, "vertex {} of face {:?} has an inward normal", i, face);
+      }
+    }
+  }
+
+  #[test]
+  fn flat_odd_surface_has_no_cracks_or_duplicate_vertices() {
+    let counts = [3, 5];
+    let builder = MeshBuilder::surface(counts, [1.0, 1.0], 0.0);
+    let row_length = MeshBuilder::surface_row_length(counts);
+    let rows = builder.vertex_count() / row_length;
+    let key = |vertex: &MeshVertex| vertex.position.map(f32::to_bits);
+    let mut positions = HashSet::new();
+
+    assert_eq!(builder.vertex_count(), 3 * 5);
+    for vertex in &builder.vertices {
+      assert_eq!(vertex.position[1], 0.0);
+      assert!(positions.insert(key(vertex)), "duplicate vertex at {:?}", vertex.position);
+    }
+
+    let mut edge_faces = HashMap::new();
+
+    assert_eq!(builder.indices.len() as u32 / 3, 2 * (row_length - 1) * (rows - 1));
+    for face in builder.indices.chunks_exact(3) {
+      assert!(!builder.is_degenerate(face), "degenerate face {:?}", face);
+      for k in 0..3 {
+        let (a, b) = (face[k], face[(k + 1) % 3]);
+
+        *edge_faces.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+      }
+    }
+
+    // Inside the grid every edge is shared by two triangles, only the border has open edges
+    let bounds = builder.bounds();
+    let on_border = |i: u32| {
+      let [x, _, z] = builder.vertices[i as usize].position;
+
+      x == bounds.min.x || x == bounds.max.x || z == bounds.min.z || z == bounds.max.z
+    };
+
+    for (&(a, b), &count) in &edge_faces {
+      assert!(count <= 2, "edge ({}, {}) is shared by {} triangles", a, b, count);
+      if count == 1 {
+        assert!(on_border(a) && on_border(b), "crack along edge ({}, {})", a, b);
+      }
+    }
+  }
+
+  // What queue.write_buffer checks: COPY_DST usage, a 4 byte aligned size, and room in the buffer
+  #[test]
+  fn instance_writes_fit_their_buffer() {
+    let mut capacity = 1;
+
+    assert!(instance::BUFFER_USAGE.contains(wgpu::BufferUsages::COPY_DST));
+    for count in [0, 1, 3, 4, 5, 100, 2] {
+      let size = (count * std::mem::size_of::<instance::InstanceRaw>()) as wgpu::BufferAddress;
+
+      capacity = InstanceBuffer::capacity_for(capacity, count);
+      assert!(count <= capacity, "{} instances written to a buffer for {}", count, capacity);
+      assert_eq!(size % wgpu::COPY_BUFFER_ALIGNMENT, 0);
+    }
+    assert_eq!(capacity, 128);
+    assert_eq!(InstanceBuffer::capacity_for(4, 5), 8);
+  }
+
+  #[test]
+  fn depth_texture_follows_resize() {
+    for (width, height) in [(800, 600), (1920, 1080), (1, 3000)] {
+      let config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: wgpu::TextureFormat::Bgra8UnormSrgb,
+        width,
+        height,
+        present_mode: wgpu::PresentMode::Fifo,
+      };
+      let descriptor = Texture::depth_descriptor(&config, Texture::DEPTH_FORMAT, "depth_texture");
+
+      assert_eq!(descriptor.size, wgpu::Extent3d { width, height, depth_or_array_layers: 1 });
+    }
+  }
+}
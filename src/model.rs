@@ -1,21 +1,118 @@
-use anyhow::Result;
-use cgmath::{InnerSpace, Vector3};
-use rand::Rng;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use std::path::Path;
+use anyhow::{anyhow, bail, Context, Result};
+use cgmath::{Deg, InnerSpace, Matrix4, Quaternion, Rotation3, Vector3};
+use ply_rs::{
+  parser::Parser,
+  ply::{DefaultElement, Property},
+};
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use std::{
+  cell::RefCell,
+  collections::HashSet,
+  fs::File,
+  io::BufReader,
+  path::Path,
+  sync::Arc,
+};
 use tobj::LoadOptions;
-use wgpu::util::DeviceExt;
 
-use crate::mesh::{Mesh, MeshBuilder, MeshVertex};
+use crate::{
+  bounds::Aabb,
+  instance::{Instance, InstanceBuffer},
+  mesh::{ColorSource, GeometryReport, Mesh, MeshBuilder, MeshVertex},
+};
 
+const BOUNDS_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 1.0];
+const EDGE_COLOR: [f32; 4] = [0.05, 0.05, 0.05, 1.0];
+const NORMAL_COLOR: [f32; 4] = [0.2, 0.6, 1.0, 1.0];
 const MODEL_COLOR: [f32;4] = [1.0, 0.1, 0.1, 1.0];
 
+// Options that only affect how files are imported, not what they contain
+#[derive(Clone, Debug, Default)]
+pub struct ImportOptions {
+  // None uses the material color for meshes that have one
+  pub color_source: Option<ColorSource>,
+  pub flip_normals: bool,
+  pub flip_winding: bool,
+  // Reads OBJ polygons as written so wireframes show their edges rather than the triangulation
+  pub keep_polygons: bool,
+  // File reading runs here when set, otherwise on rayon's global pool with a thread per core
+  pub load_pool: Option<Arc<rayon::ThreadPool>>,
+  // Scales the model so its largest dimension is this long
+  pub normalize: Option<f32>,
+  // Moves the model so its bounding box is centered on the origin
+  pub recenter: bool,
+  // Drops degenerate triangles found by validation
+  pub repair: bool,
+  // Passes of Laplacian smoothing, normals are recomputed after any
+  pub smooth_iterations: u32,
+  // Fails the load when validation finds bad geometry instead of only warning
+  pub strict: bool,
+  pub up_axis: UpAxis,
+}
+
+// The axis a file treats as up, turned to +y on import
+#[derive(clap::ArgEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum UpAxis {
+  #[default]
+  Y,
+  Z,
+}
+
+// Geometry counts gathered while importing a file
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LoadInfo {
+  pub has_normals: bool,
+  pub has_uvs: bool,
+  pub meshes: usize,
+  pub triangles: usize,
+  pub vertices: usize,
+}
+
+impl LoadInfo {
+  fn new(meshes: &[Mesh], has_normals: bool, has_uvs: bool) -> Self {
+    let triangle_meshes = meshes.iter()
+      .filter(|mesh| mesh.topology == wgpu::PrimitiveTopology::TriangleList);
+
+    Self {
+      has_normals,
+      has_uvs,
+      meshes: meshes.len(),
+      triangles: triangle_meshes.map(|mesh| mesh.num_elements as usize / 3).sum(),
+      vertices: meshes.iter().map(|mesh| mesh.num_vertices as usize).sum(),
+    }
+  }
+}
+
 pub enum ModelPrimitive {
   Cube,
-  Plane,
+  Plane { subdivisions: u32 },
 }
 
+// Describes a built-in generator and the CLI flags that shape it
+pub struct PrimitiveInfo {
+  pub flag: &'static str,
+  pub name: &'static str,
+  pub parameters: &'static [&'static str],
+}
+
+// Every built-in generator, keep in step with the Model constructors
+pub const PRIMITIVES: &[PrimitiveInfo] = &[
+  PrimitiveInfo { flag: "--cube", name: "cube", parameters: &["size"] },
+  PrimitiveInfo {
+    flag: "--fence",
+    name: "fence",
+    parameters: &["count", "fence-spacing", "width", "length", "height", "no-rails"],
+  },
+  PrimitiveInfo { flag: "--heightmap", name: "heightmap", parameters: &["size", "plane-subdivisions", "displace-scale"] },
+  PrimitiveInfo { flag: "--house", name: "house", parameters: &["width", "length", "height"] },
+  PrimitiveInfo { flag: "--plane", name: "plane", parameters: &["size", "plane-subdivisions"] },
+  PrimitiveInfo { flag: "--surface", name: "surface", parameters: &["count", "size", "max"] },
+];
+
 pub struct Model {
+  pub bounds: Aabb,
+  pub bounds_mesh: Option<Mesh>,
+  pub instances: InstanceBuffer,
   pub meshes: Vec<Mesh>,
 }
 
@@ -37,28 +134,61 @@ impl Model {
   }
 
   pub fn cube(device: &wgpu::Device, size: f32) -> Self {
-    let mut builder = MeshBuilder::new("Cube");
-    let up = size * Vector3::unit_y();
-    let right = size * Vector3::unit_x();
-    let forward = size * Vector3::unit_z();
-    let near_corner = Vector3::new(-size / 2.0, -size / 2.0, -size / 2.0);
-    let far_corner = Vector3::new(size / 2.0, size / 2.0, size / 2.0);
-
-    builder.add_quad(near_corner, forward, right);
-    builder.add_quad(near_corner, right, up);
-    builder.add_quad(near_corner, up, forward);
+    let mesh = MeshBuilder::cube(size).build(device);
 
-    builder.add_quad(far_corner, -right, -forward);
-    builder.add_quad(far_corner, -up, -right);
-    builder.add_quad(far_corner, -forward, -up);
+    Self::from_meshes(device, vec![mesh])
+  }
+
+  // A row of posts along x centered on the origin, optionally joined by two-sided rails
+  pub fn fence(
+    device: &wgpu::Device,
+    posts: u32,
+    spacing: f32,
+    width: f32,
+    length: f32,
+    height: f32,
+    rails: bool,
+  ) -> Self {
+    let mut builder = MeshBuilder::new("Fence");
+    let first = -spacing * posts.saturating_sub(1) as f32 / 2.0;
+
+    for i in 0..posts {
+      Self::add_post(&mut builder, Vector3::new(first + spacing * i as f32, 0.0, 0.0), width, length, height);
+    }
+
+    if rails {
+      let gap = (spacing - width) * Vector3::unit_x();
+      let rail_up = 0.15 * height * Vector3::unit_y();
+
+      for i in 1..posts {
+        for rail_height in [0.3, 0.7] {
+          let start = Vector3::new(first + spacing * (i - 1) as f32 + width / 2.0, rail_height * height, 0.0);
+
+          builder.add_quad(start, gap, rail_up);
+          builder.add_quad(start, rail_up, gap);
+        }
+      }
+    }
 
     let mesh = builder.build(device);
 
-    Self { meshes: vec![mesh] }
+    Self::from_meshes(device, vec![mesh])
   }
 
-  pub fn house(device: &wgpu::Device, width: f32, length: f32, height: f32) -> Self {
-    let mut builder = MeshBuilder::new("House");
+  pub fn from_meshes(device: &wgpu::Device, meshes: Vec<Mesh>) -> Self {
+    let bounds = meshes.iter().fold(Aabb::empty(), |bounds, mesh| bounds.union(mesh.bounds));
+
+    Self {
+      bounds,
+      bounds_mesh: (!bounds.is_empty()).then(|| bounds_mesh(device, &bounds)),
+      instances: InstanceBuffer::new(device, vec![Instance::default()]),
+      meshes,
+    }
+  }
+
+  // Walls and a roof, the roof built around its peak and moved up there by its mesh transform
+  pub fn house(device: &wgpu::Device, queue: &wgpu::Queue, width: f32, length: f32, height: f32) -> Self {
+    let mut walls = MeshBuilder::new("Walls");
 
     let up = height * Vector3::unit_y();
     let right = width * Vector3::unit_x();
@@ -66,18 +196,18 @@ impl Model {
     let near_corner = Vector3::new(-width / 2.0, 0.0, -length / 2.0);
     let far_corner = Vector3::new(width / 2.0, height, length / 2.0);
 
-    builder.add_quad(near_corner, right, up);
-    builder.add_quad(near_corner, up, forward);
+    walls.add_quad(near_corner, right, up);
+    walls.add_quad(near_corner, up, forward);
 
-    builder.add_quad(far_corner, -up, -right);
-    builder.add_quad(far_corner, -forward, -up);
+    walls.add_quad(far_corner, -up, -right);
+    walls.add_quad(far_corner, -forward, -up);
 
     let wall_top_left = near_corner + up;
     let wall_top_right = wall_top_left + right;
-    let mut roof_peak = wall_top_left + 0.5 * up + (width / 2.0) * right;
+    let mut roof_peak = wall_top_left + 0.5 * up + 0.5 * right;
 
-    builder.add_triangle(wall_top_left, roof_peak, wall_top_right);
-    builder.add_triangle(wall_top_left + forward, wall_top_right + forward, roof_peak + forward);
+    walls.add_triangle(wall_top_left, roof_peak, wall_top_right);
+    walls.add_triangle(wall_top_left + forward, wall_top_right + forward, roof_peak + forward);
 
     let mut from_peak_left = wall_top_left - roof_peak;
     let mut from_peak_right = wall_top_right - roof_peak;
@@ -92,26 +222,284 @@ impl Model {
     roof_peak += Vector3::unit_y() * 0.001;
     forward += Vector3::unit_z() * roof_overhang * 2.0;
 
-    builder.add_quad(roof_peak, forward, from_peak_left);
-    builder.add_quad(roof_peak, from_peak_left, forward);
-    builder.add_quad(roof_peak, from_peak_right, forward);
-    builder.add_quad(roof_peak, forward, from_peak_right);
+    let mut roof = MeshBuilder::new("Roof");
+    let peak = Vector3::new(0.0, 0.0, 0.0);
 
-    let mesh = builder.build(device);
+    // Each roof slope is added with both windings so the overhang shows from below
+    roof.add_quad(peak, forward, from_peak_left);
+    roof.add_quad(peak, from_peak_left, forward);
+    roof.add_quad(peak, from_peak_right, forward);
+    roof.add_quad(peak, forward, from_peak_right);
+
+    let mut roof_mesh = roof.build(device);
+
+    roof_mesh.set_transform(queue, Matrix4::from_translation(roof_peak));
 
-    Self { meshes: vec![mesh] }
+    Self::from_meshes(device, vec![walls.build(device), roof_mesh])
   }
 
   pub fn load<P: AsRef<Path>>(
     device: &wgpu::Device,
     path: P,
   ) -> Result<Self> {
-    let (obj_models, _) = tobj::load_obj(path.as_ref(), &LoadOptions {
-      triangulate: true,
+    Self::load_with(device, path, &ImportOptions::default())
+  }
+
+  pub fn load_with<P: AsRef<Path>>(
+    device: &wgpu::Device,
+    path: P,
+    options: &ImportOptions,
+  ) -> Result<Self> {
+    Self::load_with_info(device, path, options).map(|(model, _)| model)
+  }
+
+  pub fn load_with_info<P: AsRef<Path>>(
+    device: &wgpu::Device,
+    path: P,
+    options: &ImportOptions,
+  ) -> Result<(Self, LoadInfo)> {
+    let parts = FileParts::read(path.as_ref(), options)?;
+
+    Ok(parts.build(device, options))
+  }
+
+  // Loads each file and joins their meshes into one model that shares a single instance set.
+  // Each file is moved by its offset before the import placement runs over the whole model.
+  pub fn load_many<P: AsRef<Path>>(
+    device: &wgpu::Device,
+    files: &[(P, Vector3<f32>)],
+    options: &ImportOptions,
+  ) -> Result<Self> {
+    let mut merged = FileParts {
+      builders: Vec::new(),
+      edges: Vec::new(),
+      has_normals: true,
+      has_uvs: true,
+    };
+
+    for (path, offset) in files {
+      let path = path.as_ref();
+      let mut parts = FileParts::read(path, options)
+        .with_context(|| format!("Failed to load {}", path.display()))?;
+
+      for builder in parts.builders.iter_mut().chain(parts.edges.iter_mut().flatten()) {
+        builder.transform_positions(-*offset, 1.0);
+      }
+      merged.builders.append(&mut parts.builders);
+      merged.edges.append(&mut parts.edges);
+      merged.has_normals &= parts.has_normals;
+      merged.has_uvs &= parts.has_uvs;
+    }
+
+    Ok(merged.build(device, options).0)
+  }
+
+  // A plane grid raised by the brightness of a grayscale image, stretched over the whole plane
+  pub fn heightmap(device: &wgpu::Device, path: &Path, size: f32, subdivisions: u32, scale: f32) -> Result<Self> {
+    let image = image::open(path)
+      .map_err(|error| anyhow!("Failed to read heightmap {}: {}", path.display(), error))?
+      .into_luma8();
+    let (width, height) = image.dimensions();
+    let mut builder = grid(size, subdivisions, |u, v| {
+      let x = (u * (width - 1) as f32).round() as u32;
+      let y = (v * (height - 1) as f32).round() as u32;
+
+      scale * image.get_pixel(x, y)[0] as f32 / 255.0
+    });
+
+    builder.compute_normals();
+
+    let mesh = builder.build(device);
+
+    Ok(Self::from_meshes(device, vec![mesh]))
+  }
+
+  pub fn plane(device: &wgpu::Device, size: f32, subdivisions: u32) -> Self {
+    let mesh = grid(size, subdivisions, |_, _| 0.0).build(device);
+
+    Self::from_meshes(device, vec![mesh])
+  }
+
+  // Pushes each mesh away from the model center by factor times its own offset from it
+  pub fn set_explode(&mut self, queue: &wgpu::Queue, factor: f32) {
+    let center = self.bounds.center();
+    let mut exploded = Aabb::empty();
+
+    for mesh in self.meshes.iter_mut().filter(|mesh| !mesh.bounds.is_empty()) {
+      let offset = (mesh.bounds.center() - center) * factor;
+
+      mesh.set_offset(queue, offset);
+      exploded = exploded.union(Aabb { min: mesh.bounds.min + offset, max: mesh.bounds.max + offset });
+    }
+    // The box grows to take in the meshes where they now sit
+    if let Some(bounds_mesh) = &self.bounds_mesh {
+      queue.write_buffer(&bounds_mesh.vertex_buffer, 0, bytemuck::cast_slice(&bounds_vertices(&exploded)));
+    }
+  }
+
+  pub fn set_mesh_color(&mut self, queue: &wgpu::Queue, index: usize, color: [f32; 3]) {
+    let mesh_count = self.meshes.len();
+
+    match self.meshes.get_mut(index) {
+      Some(mesh) => mesh.set_color(queue, color),
+      None => log::warn!("Ignoring color for mesh {}, the model only has {} meshes", index, mesh_count),
+    }
+  }
+
+  pub fn set_mesh_emissive(&mut self, queue: &wgpu::Queue, index: usize, color: [f32; 3]) {
+    let mesh_count = self.meshes.len();
+
+    match self.meshes.get_mut(index) {
+      Some(mesh) => mesh.set_emissive(queue, color),
+      None => log::warn!("Ignoring emissive for mesh {}, the model only has {} meshes", index, mesh_count),
+    }
+  }
+
+  pub fn set_mesh_visible(&mut self, index: usize, visible: bool) {
+    let mesh_count = self.meshes.len();
+
+    match self.meshes.get_mut(index) {
+      Some(mesh) => mesh.visible = visible,
+      None => log::warn!("Ignoring visibility for mesh {}, the model only has {} meshes", index, mesh_count),
+    }
+  }
+
+  // A segment of length along each vertex normal of each mesh, built the first time it's asked for
+  pub fn build_normals_meshes(&mut self, device: &wgpu::Device, length: f32) {
+    for mesh in self.meshes.iter_mut().filter(|mesh| mesh.normals.is_none() && mesh.num_vertices > 0) {
+      let mut builder = MeshBuilder::new("Normals");
+
+      for vertex in &mesh.vertices {
+        let position = Vector3::from(vertex.position);
+        let index = builder.vertex_count();
+
+        builder.add_colored_vertex(position, vertex.normal, NORMAL_COLOR);
+        builder.add_colored_vertex(position + Vector3::from(vertex.normal) * length, vertex.normal, NORMAL_COLOR);
+        builder.add_edge((index, index + 1));
+      }
+      builder.set_topology(wgpu::PrimitiveTopology::LineList);
+
+      mesh.normals = Some(Box::new(builder.build_overlay(device, mesh)));
+    }
+  }
+
+  pub fn set_draw_limit(&mut self, limit: Option<u32>) {
+    self.instances.set_limit(limit);
+  }
+
+  pub fn set_instances(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, instances: Vec<Instance>) {
+    self.instances.update(device, queue, instances);
+  }
+
+  // counts and sizes are [x, z], the columns and rows of the grid and the spacing along each. With
+  // quad_edges the wireframe leaves out the diagonals that split each cell.
+  pub fn surface(device: &wgpu::Device, counts: [u32; 2], sizes: [f32; 2], height_max: f32, quad_edges: bool) -> Self {
+    let builder = MeshBuilder::surface(counts, sizes, height_max);
+    let row_length = MeshBuilder::surface_row_length(counts);
+    let mut edges = MeshBuilder::new("Edges");
+
+    for vertex in builder.vertices() {
+      edges.add_colored_vertex(vertex.position, vertex.normal, EDGE_COLOR);
+    }
+    for index in 0..builder.vertex_count() {
+      let (row, col) = (index / row_length, index % row_length);
+
+      if col > 0 {
+        edges.add_edge((index - 1, index));
+      }
+      if row > 0 {
+        edges.add_edge((index - row_length, index));
+      }
+      // The same diagonal add_linked_quad splits the cell along
+      if !quad_edges && row > 0 && col > 0 {
+        edges.add_edge((index - row_length, index - 1));
+      }
+    }
+    edges.unweld();
+    edges.set_topology(wgpu::PrimitiveTopology::LineList);
+
+    let mut mesh = builder.build(device);
+
+    mesh.edges = Some(Box::new(edges.build_overlay(device, &mesh)));
+
+    Self::from_meshes(device, vec![mesh])
+  }
+
+  // Bounds of every instance in world space
+  pub fn world_bounds(&self) -> Aabb {
+    if self.bounds.is_empty() {
+      return self.bounds;
+    }
+
+    self.instances.instances.iter().fold(Aabb::empty(), |bounds, instance| {
+      self.bounds.corners().iter().fold(bounds, |bounds, &corner| {
+        bounds.grow(instance.position + instance.rotation * (corner * instance.scale))
+      })
+    })
+  }
+}
+
+// Mesh builders read from one file, fixed up and validated but not yet placed or uploaded
+struct FileParts {
+  builders: Vec<MeshBuilder>,
+  // Polygon outlines of each builder, for the formats that keep them
+  edges: Vec<Option<MeshBuilder>>,
+  has_normals: bool,
+  has_uvs: bool,
+}
+
+impl FileParts {
+  fn read(path: &Path, options: &ImportOptions) -> Result<Self> {
+    match &options.load_pool {
+      Some(pool) => pool.install(|| Self::read_file(path, options)),
+      None => Self::read_file(path, options),
+    }
+  }
+
+  fn read_file(path: &Path, options: &ImportOptions) -> Result<Self> {
+    let is_ply = path.extension().map_or(false, |extension| extension.eq_ignore_ascii_case("ply"));
+
+    let mut parts = if is_ply {
+      Self::read_ply(path, options)?
+    } else {
+      Self::read_obj(path, options)?
+    };
+
+    // A quarter turn about x takes +z up to +y and keeps the winding
+    if options.up_axis == UpAxis::Z {
+      let rotation = Quaternion::from_angle_x(Deg(-90.0));
+
+      for builder in parts.builders.iter_mut().chain(parts.edges.iter_mut().flatten()) {
+        builder.rotate(rotation);
+      }
+    }
+
+    Ok(parts)
+  }
+
+  fn read_obj(path: &Path, options: &ImportOptions) -> Result<Self> {
+    // tobj leaves a missing Kd black, so the materials that do set one are noted while loading
+    let diffuse_materials = RefCell::new(HashSet::new());
+    let mut reader = BufReader::new(File::open(path)?);
+    let (obj_models, materials) = tobj::load_obj_buf(&mut reader, &LoadOptions {
+      triangulate: !options.keep_polygons,
       single_index: true,
       ..Default::default()
+    }, |mtl_path| {
+      let mtl_path = path.parent().map_or_else(|| mtl_path.to_path_buf(), |parent| parent.join(mtl_path));
+      let text = std::fs::read_to_string(&mtl_path).map_err(|_| tobj::LoadError::OpenFileFailed)?;
+
+      diffuse_materials.borrow_mut().extend(materials_with_diffuse(&text));
+      tobj::load_mtl_buf(&mut text.as_bytes())
     })?;
-    let meshes = obj_models.iter().map(|m| {
+    let diffuse_materials = diffuse_materials.into_inner();
+    let materials = materials.unwrap_or_else(|error| {
+      log::debug!("No materials for {:?}: {}", path, error);
+      Vec::new()
+    });
+    let mut meshes = obj_models.iter().map(|m| {
+      let (indices, polygon_edges) = polygons(&m.mesh);
+      let has_normals = !m.mesh.normals.is_empty();
       let vertices = (0..m.mesh.positions.len() / 3).into_par_iter().map(|i| {
         MeshVertex {
           position: [
@@ -119,76 +507,373 @@ impl Model {
             m.mesh.positions[i * 3 + 1],
             m.mesh.positions[i * 3 + 2],
           ].into(),
-          normal: [
-            m.mesh.normals[i * 3],
-            m.mesh.normals[i * 3 + 1],
-            m.mesh.normals[i * 3 + 2],
-          ].into(),
-          color: MODEL_COLOR,
+          normal: if has_normals {
+            [
+              m.mesh.normals[i * 3],
+              m.mesh.normals[i * 3 + 1],
+              m.mesh.normals[i * 3 + 2],
+            ]
+          } else {
+            [0.0, 1.0, 0.0]
+          },
+          color: obj_color(&m.mesh, i),
         }
       }).collect::<Vec<_>>();
+      let mut builder = MeshBuilder::from_geometry(&m.name, vertices, indices);
 
-      let vertex_buffer = device.create_buffer_init(
-        &wgpu::util::BufferInitDescriptor {
-          label: Some(&format!("{:?} Vertex Buffer", path.as_ref())),
-          contents: bytemuck::cast_slice(&vertices),
-          usage: wgpu::BufferUsages::VERTEX,
+      builder.set_material(m.mesh.material_id.unwrap_or(0));
+      if let Some(material) = m.mesh.material_id.and_then(|id| materials.get(id)) {
+        if diffuse_materials.contains(&material.name) {
+          builder.set_material_color(material.diffuse);
         }
-      );
-      let index_buffer = device.create_buffer_init(
-        &wgpu::util::BufferInitDescriptor {
-          label: Some(&format!("{:?} Index Buffer", path.as_ref())),
-          contents: bytemuck::cast_slice(&m.mesh.indices),
-          usage: wgpu::BufferUsages::INDEX,
+        if let Some(emissive) = material.unknown_param.get("Ke").and_then(|value| parse_color(value)) {
+          builder.set_emissive(emissive);
         }
-      );
-
-      Ok(Mesh {
-        name: String::from(&m.name),
-        vertex_buffer,
-        index_buffer,
-        num_elements: m.mesh.indices.len() as u32,
-        material: m.mesh.material_id.unwrap_or(0),
-      })
-    }).collect::<Result<Vec<_>>>()?;
+      }
 
-    Ok(Self { meshes })
-  }
+      (builder, has_normals, polygon_edges)
+    }).collect::<Vec<_>>();
 
-  pub fn plane(device: &wgpu::Device, size: f32) -> Self {
-    let mut builder = MeshBuilder::new("Plane");
+    // Before fix_up, so normals computed from a bad position aren't reported as well
+    validate(meshes.iter_mut().map(|(builder, _, _)| builder).collect(), path, options)?;
 
-    builder.add_quad(
-      Vector3::new(-size / 2.0, 0.0, -size / 2.0),
-      Vector3::new(size, 0.0, 0.0),
-      Vector3::new(0.0, 0.0, size),
-    );
+    let (builders, edges) = meshes.into_iter().map(|(mut builder, has_normals, polygon_edges)| {
+      fix_up(&mut builder, has_normals, options);
 
-    let mesh = builder.build(device);
+      // After fix_up, so the outlines follow smoothed positions
+      let mut edges = MeshBuilder::new("Edges");
+
+      for vertex in builder.vertices() {
+        edges.add_colored_vertex(vertex.position, vertex.normal, EDGE_COLOR);
+      }
+      for edge in polygon_edges {
+        edges.add_edge(edge);
+      }
 
-    Self { meshes: vec![mesh] }
+      (builder, Some(edges))
+    }).unzip();
+
+    Ok(FileParts {
+      builders,
+      edges,
+      has_normals: obj_models.iter().all(|m| !m.mesh.normals.is_empty()),
+      has_uvs: obj_models.iter().all(|m| !m.mesh.texcoords.is_empty()),
+    })
   }
 
-  pub fn surface(device: &wgpu::Device, count: u32, size: f32, height_max: f32) -> Self {
-    let mut builder = MeshBuilder::new("Quad Grid");
-    let half_count = count as i32 / 2;
-    let mut rng = rand::thread_rng();
+  fn read_ply(path: &Path, options: &ImportOptions) -> Result<Self> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let ply = Parser::<DefaultElement>::new().read_ply(&mut reader)?;
+    let ply_vertices = ply.payload.get("vertex")
+      .ok_or_else(|| anyhow!("{:?} has no vertex element", path))?;
+    let has_normals = ply_vertices.first().map_or(false, |v| v.contains_key("nx"));
+    let has_uvs = ply_vertices.first()
+      .map_or(false, |v| ["u", "s", "texture_u"].iter().any(|key| v.contains_key(*key)));
+    let vertices = ply_vertices.par_iter().map(|v| {
+      let position = ["x", "y", "z"].map(|key| ply_scalar(v, key).unwrap_or(0.0));
+      let normal = if has_normals {
+        ["nx", "ny", "nz"].map(|key| ply_scalar(v, key).unwrap_or(0.0))
+      } else {
+        [0.0, 1.0, 0.0]
+      };
+      let color = match ["red", "green", "blue"].map(|key| ply_color(v, key)) {
+        [Some(r), Some(g), Some(b)] => [r, g, b, ply_color(v, "alpha").unwrap_or(1.0)],
+        _ => MODEL_COLOR,
+      };
+
+      MeshVertex { position, normal, color }
+    }).collect::<Vec<_>>();
+    let mut indices = Vec::new();
+
+    for (face_number, face) in ply.payload.get("face").into_iter().flatten().enumerate() {
+      let polygon = face.get("vertex_indices")
+        .or_else(|| face.get("vertex_index"))
+        .map(ply_indices)
+        .unwrap_or_default();
+
+      if let Some(index) = polygon.iter().find(|&&index| index as usize >= vertices.len()) {
+        bail!("{:?} face {} uses vertex {}, but there are only {} vertices", path, face_number, index, vertices.len());
+      }
+      indices.extend((1..polygon.len().saturating_sub(1)).flat_map(|i| [polygon[0], polygon[i], polygon[i + 1]]));
+    }
+    let label = format!("{:?}", path);
+
+    let mut builder = if indices.is_empty() {
+      let point_indices = (0..vertices.len() as u32).collect();
+      let mut builder = MeshBuilder::from_geometry(&label, vertices, point_indices);
+
+      builder.set_topology(wgpu::PrimitiveTopology::PointList);
+      builder
+    } else {
+      MeshBuilder::from_geometry(&label, vertices, indices)
+    };
+
+    validate(vec![&mut builder], path, options)?;
+    fix_up(&mut builder, has_normals, options);
+
+    Ok(FileParts {
+      builders: vec![builder],
+      edges: vec![None],
+      has_normals,
+      has_uvs,
+    })
+  }
 
-    for i in -half_count..half_count + 1 {
-      let z = 2.0 * size * i as f32;
+  fn build(mut self, device: &wgpu::Device, options: &ImportOptions) -> (Model, LoadInfo) {
+    place(self.builders.iter_mut().chain(self.edges.iter_mut().flatten()).collect(), options);
+    for builder in &mut self.builders {
+      builder.set_color_source(options.color_source);
+    }
 
-      for j in -half_count..half_count + 1 {
-        let x = 2.0 * size * j as f32;
-        let y = rng.gen_range(0.0..height_max);
-        let position = Vector3::new(x, y, z);
-        let link = i > -half_count && j > -half_count;
+    let mut meshes = self.builders.iter().map(|builder| builder.build(device)).collect::<Vec<_>>();
+    let info = LoadInfo::new(&meshes, self.has_normals, self.has_uvs);
 
-        builder.add_linked_quad(position, link, count + 1);
+    // Thick wires read each edge as a pair of consecutive vertices
+    for (mesh, edges) in meshes.iter_mut().zip(self.edges) {
+      if let Some(mut edges) = edges {
+        edges.unweld();
+        edges.set_topology(wgpu::PrimitiveTopology::LineList);
+        if edges.vertex_count() > 0 {
+          mesh.edges = Some(Box::new(edges.build_overlay(device, mesh)));
+        }
       }
     }
 
-    let mesh = builder.build(device);
+    (Model::from_meshes(device, meshes), info)
+  }
+}
+
+fn bounds_mesh(device: &wgpu::Device, bounds: &Aabb) -> Mesh {
+  let mut builder = MeshBuilder::from_geometry("Bounds", bounds_vertices(bounds).to_vec(), Vec::new());
+
+  // Corners differing in exactly one bit share an edge
+  for i in 0..8u32 {
+    for bit in [1, 2, 4] {
+      if i & bit == 0 {
+        builder.add_edge((i, i | bit));
+      }
+    }
+  }
+  builder.set_topology(wgpu::PrimitiveTopology::LineList);
+
+  // Rewritten in place when the model is exploded
+  builder.build_writable(device)
+}
+
+fn bounds_vertices(bounds: &Aabb) -> [MeshVertex; 8] {
+  bounds.corners().map(|corner| MeshVertex { position: corner.into(), normal: [0.0, 1.0, 0.0], color: BOUNDS_COLOR })
+}
+
+// A square grid of subdivisions by subdivisions quads on the ground, wound the same way as
+// add_quad. Heights are looked up from coordinates running 0..1 across the grid.
+fn grid<H>(size: f32, subdivisions: u32, height: H) -> MeshBuilder
+where
+  H: Fn(f32, f32) -> f32,
+{
+  let mut builder = MeshBuilder::new("Plane");
+  let subdivisions = subdivisions.max(1);
+  let step = size / subdivisions as f32;
+  let row = subdivisions + 1;
+
+  for i in 0..row {
+    for j in 0..row {
+      let u = j as f32 / subdivisions as f32;
+      let v = i as f32 / subdivisions as f32;
+      let x = -size / 2.0 + step * j as f32;
+      let z = -size / 2.0 + step * i as f32;
+
+      builder.add_vertex(Vector3::new(x, height(u, v), z), Vector3::unit_y());
+    }
+  }
+  for i in 0..subdivisions {
+    for j in 0..subdivisions {
+      let near = i * row + j;
+      let far = near + row;
+
+      builder.add_face((near, far, far + 1));
+      builder.add_face((near, far + 1, near + 1));
+    }
+  }
+
+  builder
+}
+
+// Applies the import fix-ups, flipping winding before any missing normals are computed from it
+fn fix_up(builder: &mut MeshBuilder, has_normals: bool, options: &ImportOptions) {
+  if builder.topology() != wgpu::PrimitiveTopology::TriangleList {
+    if options.flip_normals {
+      builder.flip_normals();
+    }
+    return;
+  }
+
+  if options.flip_winding {
+    builder.flip_winding();
+  }
+  if options.smooth_iterations > 0 {
+    builder.smooth(options.smooth_iterations);
+  }
+  if !has_normals || options.smooth_iterations > 0 {
+    builder.compute_normals();
+  }
+  if options.flip_normals {
+    builder.flip_normals();
+  }
+}
+
+// Recenters and normalizes the builders of one model together, using their combined bounds
+fn place(builders: Vec<&mut MeshBuilder>, options: &ImportOptions) {
+  if !options.recenter && options.normalize.is_none() {
+    return;
+  }
+
+  let bounds = builders.iter().fold(Aabb::empty(), |bounds, builder| bounds.union(builder.bounds()));
+
+  if bounds.is_empty() {
+    return;
+  }
+
+  let size = bounds.size();
+  let largest = size.x.max(size.y).max(size.z);
+  let origin = if options.recenter { bounds.center() } else { Vector3::new(0.0, 0.0, 0.0) };
+  let scale = match options.normalize {
+    Some(target) if largest > 0.0 => target / largest,
+    _ => 1.0,
+  };
+
+  for builder in builders {
+    builder.transform_positions(origin, scale);
+  }
+}
+
+// Logs a summary of bad geometry across the builders of one model, then repairs or rejects it
+fn validate(builders: Vec<&mut MeshBuilder>, path: &Path, options: &ImportOptions) -> Result<()> {
+  let report = builders.iter()
+    .map(|builder| builder.validate())
+    .fold(GeometryReport::default(), GeometryReport::combine);
+
+  if report.is_clean() {
+    return Ok(());
+  }
+
+  let summary = format!(
+    "{:?} has {} degenerate triangles, {} non-finite positions and {} non-finite normals",
+    path,
+    report.degenerate_triangles,
+    report.non_finite_positions,
+    report.non_finite_normals,
+  );
+
+  if options.strict {
+    bail!(summary);
+  }
+  log::warn!("{}", summary);
+
+  if options.repair {
+    let removed = builders.into_iter().map(MeshBuilder::remove_degenerate_faces).sum::<usize>();
+
+    log::info!("Removed {} degenerate triangles from {:?}", removed, path);
+  }
+
+  Ok(())
+}
+
+// Fans each polygon into triangles for drawing and collects its outline edges, once per shared edge
+fn polygons(mesh: &tobj::Mesh) -> (Vec<u32>, Vec<(u32, u32)>) {
+  let mut triangles = Vec::with_capacity(mesh.indices.len());
+  let mut edges = HashSet::new();
+  let mut start = 0;
+  // An empty arity list means every face is already a triangle
+  let arities = if mesh.face_arities.is_empty() {
+    vec![3; mesh.indices.len() / 3]
+  } else {
+    mesh.face_arities.iter().map(|&arity| arity as usize).collect()
+  };
+
+  for arity in arities {
+    let face = &mesh.indices[start..start + arity];
+
+    for i in 1..arity.saturating_sub(1) {
+      triangles.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+    }
+    for i in 0..arity {
+      let (a, b) = (face[i], face[(i + 1) % arity]);
+
+      edges.insert((a.min(b), a.max(b)));
+    }
+    start += arity;
+  }
+
+  (triangles, edges.into_iter().collect())
+}
+
+// Extended OBJ vertex lines can carry an rgb color after the position
+fn obj_color(mesh: &tobj::Mesh, index: usize) -> [f32; 4] {
+  match mesh.vertex_color.get(index * 3..index * 3 + 3) {
+    Some(&[r, g, b]) => [r, g, b, 1.0],
+    _ => MODEL_COLOR,
+  }
+}
+
+// Names of the materials in an MTL file that have a Kd line, matching how tobj reads newmtl
+fn materials_with_diffuse(text: &str) -> HashSet<String> {
+  let mut names = HashSet::new();
+  let mut name = None;
+
+  for line in text.lines().map(str::trim) {
+    match line.split_whitespace().next() {
+      Some("newmtl") => name = Some(line[6..].trim().to_owned()),
+      Some("Kd") => names.extend(name.clone()),
+      _ => {}
+    }
+  }
+
+  names
+}
+
+// MTL statements tobj doesn't know, like Ke, are left as the text after the keyword
+fn parse_color(value: &str) -> Option<[f32; 3]> {
+  let channels = value.split_whitespace().map(|channel| channel.parse().ok()).collect::<Option<Vec<f32>>>()?;
+
+  match channels[..] {
+    [r, g, b] => Some([r, g, b]),
+    _ => None,
+  }
+}
+
+fn ply_scalar(element: &DefaultElement, key: &str) -> Option<f32> {
+  match element.get(key)? {
+    Property::Char(value) => Some(*value as f32),
+    Property::UChar(value) => Some(*value as f32),
+    Property::Short(value) => Some(*value as f32),
+    Property::UShort(value) => Some(*value as f32),
+    Property::Int(value) => Some(*value as f32),
+    Property::UInt(value) => Some(*value as f32),
+    Property::Float(value) => Some(*value),
+    Property::Double(value) => Some(*value as f32),
+    _ => None,
+  }
+}
+
+// Integer color channels are normalized by their range, float channels are already 0..1
+fn ply_color(element: &DefaultElement, key: &str) -> Option<f32> {
+  match element.get(key)? {
+    Property::UChar(value) => Some(*value as f32 / u8::MAX as f32),
+    Property::UShort(value) => Some(*value as f32 / u16::MAX as f32),
+    Property::Float(value) => Some(*value),
+    Property::Double(value) => Some(*value as f32),
+    _ => None,
+  }
+}
 
-    Self { meshes: vec![mesh] }
+fn ply_indices(property: &Property) -> Vec<u32> {
+  match property {
+    Property::ListChar(list) => list.iter().map(|&i| i as u32).collect(),
+    Property::ListUChar(list) => list.iter().map(|&i| i as u32).collect(),
+    Property::ListShort(list) => list.iter().map(|&i| i as u32).collect(),
+    Property::ListUShort(list) => list.iter().map(|&i| i as u32).collect(),
+    Property::ListInt(list) => list.iter().map(|&i| i as u32).collect(),
+    Property::ListUInt(list) => list.clone(),
+    _ => Vec::new(),
   }
 }
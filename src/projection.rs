@@ -1,4 +1,5 @@
-use cgmath::{Matrix4, perspective, Rad};
+use anyhow::{bail, Result};
+use cgmath::{Deg, Matrix4, perspective, Rad, Vector2, Zero};
 
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
@@ -8,10 +9,25 @@ pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
     0.0, 0.0, 0.5, 1.0,
 );
 
+// Parses W:H, or a plain width over height ratio
+pub fn parse_aspect(value: &str) -> Result<f32> {
+  let aspect = match value.split_once(':') {
+    Some((width, height)) => width.parse::<f32>()? / height.parse::<f32>()?,
+    None => value.parse()?,
+  };
+
+  if !aspect.is_finite() || aspect <= 0.0 {
+    bail!("Expected a positive aspect like 16:9, got {}", value);
+  }
+
+  Ok(aspect)
+}
 
 pub struct Projection {
   aspect: f32,
+  aspect_lock: Option<f32>,
   fovy: Rad<f32>,
+  jitter: Vector2<f32>,
   znear: f32,
   zfar: f32,
 }
@@ -26,17 +42,55 @@ impl Projection {
   ) -> Self {
     Self {
       aspect: width as f32 / height as f32,
+      aspect_lock: None,
       fovy: fovy.into(),
+      jitter: Vector2::zero(),
       znear,
       zfar,
     }
   }
 
+  pub fn fovy(&self) -> Deg<f32> {
+    self.fovy.into()
+  }
+
+  pub fn aspect_lock(&self) -> Option<f32> {
+    self.aspect_lock
+  }
+
+  // Keeps the last aspect for a zero sized window rather than dividing by zero
   pub fn resize(&mut self, width: u32, height: u32) {
-    self.aspect = width as f32 / height as f32;
+    if let Some(aspect) = self.aspect_lock {
+      self.aspect = aspect;
+    } else if width > 0 && height > 0 {
+      self.aspect = width as f32 / height as f32;
+    }
+  }
+
+  // A locked aspect ignores the window shape, the renderer letterboxes to match it
+  pub fn set_aspect_lock(&mut self, aspect_lock: Option<f32>, width: u32, height: u32) {
+    self.aspect_lock = aspect_lock;
+    self.resize(width, height);
+  }
+
+  pub fn set_fovy(&mut self, fovy: Deg<f32>) {
+    self.fovy = Deg(fovy.0.clamp(10.0, 120.0)).into();
+  }
+
+  // Offset of the whole image in clip space, used for sub-pixel jitter
+  pub fn set_jitter(&mut self, jitter: Vector2<f32>) {
+    self.jitter = jitter;
+  }
+
+  pub fn zoom(&mut self, delta: Deg<f32>) {
+    self.set_fovy(self.fovy() + delta);
   }
 
   pub fn calc_matrix(&self) -> Matrix4<f32> {
+    Matrix4::from_translation(self.jitter.extend(0.0)) * self.calc_unjittered_matrix()
+  }
+
+  pub fn calc_unjittered_matrix(&self) -> Matrix4<f32> {
     OPENGL_TO_WGPU_MATRIX * perspective(self.fovy, self.aspect, self.znear, self.zfar)
   }
 }
@@ -0,0 +1,217 @@
+use cgmath::Vector2;
+
+use crate::{
+  render::{create_render_pipeline, PipelineOptions},
+  texture::Texture,
+};
+
+const ACCUMULATION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const JITTER_SEQUENCE_LENGTH: u32 = 16;
+const MAX_ACCUMULATED_FRAMES: u32 = 64;
+
+// Offscreen targets for one window size. The scene is drawn into the frame texture,
+// blended into the accumulation texture and then copied to the surface.
+pub struct Accumulation {
+  accumulate_bind_group: wgpu::BindGroup,
+  accumulation_texture: Texture,
+  blit_bind_group: wgpu::BindGroup,
+  frame_texture: Texture,
+  frames: u32,
+  size: Vector2<f32>,
+}
+
+impl Accumulation {
+  pub fn frame_view(&self) -> &wgpu::TextureView {
+    &self.frame_texture.view
+  }
+
+  // Sub-pixel offset in clip space for the next frame from a Halton (2, 3) sequence
+  pub fn jitter(&self) -> Vector2<f32> {
+    let index = self.frames % JITTER_SEQUENCE_LENGTH + 1;
+
+    Vector2::new(
+      (halton(index, 2) - 0.5) * 2.0 / self.size.x,
+      (halton(index, 3) - 0.5) * 2.0 / self.size.y,
+    )
+  }
+
+  pub fn reset(&mut self) {
+    self.frames = 0;
+  }
+}
+
+pub struct AccumulateRenderer {
+  accumulate_pipeline: wgpu::RenderPipeline,
+  bind_group_layout: wgpu::BindGroupLayout,
+  blit_pipeline: wgpu::RenderPipeline,
+}
+
+impl AccumulateRenderer {
+  pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      entries: &[
+        wgpu::BindGroupLayoutEntry {
+          binding: 0,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+          },
+          count: None,
+        }
+      ],
+      label: Some("accumulate_bind_group_layout"),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("Accumulate Pipeline Layout"),
+      bind_group_layouts: &[&bind_group_layout],
+      push_constant_ranges: &[],
+    });
+    let shader = || wgpu::ShaderModuleDescriptor {
+      label: Some("Accumulate Shader"),
+      source: wgpu::ShaderSource::Wgsl(
+        concat!(include_str!("shaders/fullscreen.wgsl"), include_str!("shaders/accumulate.wgsl")).into()
+      ),
+    };
+    // Blends the new frame in with the constant set per frame as 1 / frame count
+    let blend_component = wgpu::BlendComponent {
+      src_factor: wgpu::BlendFactor::Constant,
+      dst_factor: wgpu::BlendFactor::OneMinusConstant,
+      operation: wgpu::BlendOperation::Add,
+    };
+    let accumulate_pipeline = create_render_pipeline(
+      device,
+      &pipeline_layout,
+      ACCUMULATION_FORMAT,
+      None,
+      &[],
+      shader(),
+      PipelineOptions {
+        blend: wgpu::BlendState {
+          alpha: blend_component,
+          color: blend_component,
+        },
+        vertex_entry: "vs_fullscreen",
+        ..PipelineOptions::new("Accumulate Render Pipeline")
+      },
+    );
+    let blit_pipeline = create_render_pipeline(
+      device,
+      &pipeline_layout,
+      format,
+      None,
+      &[],
+      shader(),
+      PipelineOptions {
+        vertex_entry: "vs_fullscreen",
+        ..PipelineOptions::new("Blit Render Pipeline")
+      },
+    );
+
+    Self {
+      accumulate_pipeline,
+      bind_group_layout,
+      blit_pipeline,
+    }
+  }
+
+  pub fn create_accumulation(&self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Accumulation {
+    let frame_texture = Texture::create_render_target(device, config, config.format, "frame_texture");
+    let accumulation_texture = Texture::create_render_target(device, config, ACCUMULATION_FORMAT, "accumulation_texture");
+    let create_bind_group = |texture: &Texture, label| device.create_bind_group(&wgpu::BindGroupDescriptor {
+      layout: &self.bind_group_layout,
+      entries: &[
+        wgpu::BindGroupEntry {
+          binding: 0,
+          resource: wgpu::BindingResource::TextureView(&texture.view),
+        }
+      ],
+      label: Some(label),
+    });
+    let accumulate_bind_group = create_bind_group(&frame_texture, "accumulate_bind_group");
+    let blit_bind_group = create_bind_group(&accumulation_texture, "blit_bind_group");
+
+    Accumulation {
+      accumulate_bind_group,
+      accumulation_texture,
+      blit_bind_group,
+      frame_texture,
+      frames: 0,
+      size: Vector2::new(config.width as f32, config.height as f32),
+    }
+  }
+
+  // Once enough frames are in, the history is kept as a moving average so later
+  // scene changes still blend in
+  pub fn render(
+    &self,
+    encoder: &mut wgpu::CommandEncoder,
+    accumulation: &mut Accumulation,
+    view: &wgpu::TextureView,
+  ) {
+    let weight = 1.0 / (accumulation.frames.min(MAX_ACCUMULATED_FRAMES - 1) + 1) as f64;
+    let load = if accumulation.frames == 0 {
+      wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+    } else {
+      wgpu::LoadOp::Load
+    };
+
+    {
+      let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Accumulate Pass"),
+        color_attachments: &[
+          wgpu::RenderPassColorAttachment {
+            view: &accumulation.accumulation_texture.view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+              load,
+              store: true,
+            },
+          }
+        ],
+        depth_stencil_attachment: None,
+      });
+
+      render_pass.set_pipeline(&self.accumulate_pipeline);
+      render_pass.set_blend_constant(wgpu::Color { r: weight, g: weight, b: weight, a: weight });
+      render_pass.set_bind_group(0, &accumulation.accumulate_bind_group, &[]);
+      render_pass.draw(0..3, 0..1);
+    }
+    {
+      let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Blit Pass"),
+        color_attachments: &[
+          wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+              load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+              store: true,
+            },
+          }
+        ],
+        depth_stencil_attachment: None,
+      });
+
+      render_pass.set_pipeline(&self.blit_pipeline);
+      render_pass.set_bind_group(0, &accumulation.blit_bind_group, &[]);
+      render_pass.draw(0..3, 0..1);
+    }
+
+    accumulation.frames = accumulation.frames.saturating_add(1);
+  }
+}
+
+fn halton(mut index: u32, base: u32) -> f32 {
+  let mut fraction = 1.0;
+  let mut result = 0.0;
+
+  while index > 0 {
+    fraction /= base as f32;
+    result += fraction * (index % base) as f32;
+    index /= base;
+  }
+
+  result
+}
@@ -0,0 +1,112 @@
+use crate::{
+  render::{create_render_pipeline, PipelineOptions},
+  texture::Texture,
+};
+
+// Offscreen copy of the surface the scene is drawn into before it is blitted to the window,
+// so each finished frame can be read back without drawing it twice
+pub struct FrameTarget {
+  bind_group: wgpu::BindGroup,
+  texture: Texture,
+}
+
+impl FrameTarget {
+  pub fn texture(&self) -> &Texture {
+    &self.texture
+  }
+
+  pub fn view(&self) -> &wgpu::TextureView {
+    &self.texture.view
+  }
+}
+
+pub struct BlitRenderer {
+  bind_group_layout: wgpu::BindGroupLayout,
+  render_pipeline: wgpu::RenderPipeline,
+}
+
+impl BlitRenderer {
+  pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      entries: &[
+        wgpu::BindGroupLayoutEntry {
+          binding: 0,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+          },
+          count: None,
+        }
+      ],
+      label: Some("blit_bind_group_layout"),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("Blit Pipeline Layout"),
+      bind_group_layouts: &[&bind_group_layout],
+      push_constant_ranges: &[],
+    });
+    // The accumulation shader is already a plain texel copy
+    let shader = wgpu::ShaderModuleDescriptor {
+      label: Some("Blit Shader"),
+      source: wgpu::ShaderSource::Wgsl(
+        concat!(include_str!("shaders/fullscreen.wgsl"), include_str!("shaders/accumulate.wgsl")).into()
+      ),
+    };
+    let render_pipeline = create_render_pipeline(
+      device,
+      &pipeline_layout,
+      format,
+      None,
+      &[],
+      shader,
+      PipelineOptions {
+        vertex_entry: "vs_fullscreen",
+        ..PipelineOptions::new("Frame Blit Render Pipeline")
+      },
+    );
+
+    Self {
+      bind_group_layout,
+      render_pipeline,
+    }
+  }
+
+  pub fn create_frame_target(&self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> FrameTarget {
+    let texture = Texture::create_render_target(device, config, config.format, "frame_target");
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+      layout: &self.bind_group_layout,
+      entries: &[
+        wgpu::BindGroupEntry {
+          binding: 0,
+          resource: wgpu::BindingResource::TextureView(&texture.view),
+        }
+      ],
+      label: Some("frame_target_bind_group"),
+    });
+
+    FrameTarget { bind_group, texture }
+  }
+
+  pub fn render(&self, encoder: &mut wgpu::CommandEncoder, frame_target: &FrameTarget, view: &wgpu::TextureView) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+      label: Some("Frame Blit Pass"),
+      color_attachments: &[
+        wgpu::RenderPassColorAttachment {
+          view,
+          resolve_target: None,
+          ops: wgpu::Operations {
+            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            store: true,
+          },
+        }
+      ],
+      depth_stencil_attachment: None,
+    });
+
+    render_pass.set_pipeline(&self.render_pipeline);
+    render_pass.set_bind_group(0, &frame_target.bind_group, &[]);
+    render_pass.draw(0..3, 0..1);
+  }
+}
@@ -0,0 +1,74 @@
+use crate::render::{create_render_pipeline, PipelineOptions};
+
+// Fills the color target with a screen space checkerboard so blended geometry shows what is behind it
+pub struct CheckerRenderer {
+  render_pipeline: wgpu::RenderPipeline,
+}
+
+impl CheckerRenderer {
+  pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+    let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("Checker Pipeline Layout"),
+      bind_group_layouts: &[],
+      push_constant_ranges: &[],
+    });
+    let shader = wgpu::ShaderModuleDescriptor {
+      label: Some("Checker Shader"),
+      source: wgpu::ShaderSource::Wgsl(
+        concat!(include_str!("shaders/fullscreen.wgsl"), include_str!("shaders/checker.wgsl")).into()
+      ),
+    };
+    let render_pipeline = create_render_pipeline(
+      device,
+      &render_pipeline_layout,
+      format,
+      None,
+      &[],
+      shader,
+      PipelineOptions {
+        vertex_entry: "vs_fullscreen",
+        ..PipelineOptions::new("Checker Render Pipeline")
+      },
+    );
+
+    Self { render_pipeline }
+  }
+
+  pub fn render(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+    self.render_pass(encoder, view, wgpu::LoadOp::Clear(wgpu::Color::BLACK), None);
+  }
+
+  // Fills only the [x, y, width, height] rectangle, keeping the rest of the target
+  pub fn render_region(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, region: [u32; 4]) {
+    self.render_pass(encoder, view, wgpu::LoadOp::Load, Some(region));
+  }
+
+  fn render_pass(
+    &self,
+    encoder: &mut wgpu::CommandEncoder,
+    view: &wgpu::TextureView,
+    load: wgpu::LoadOp<wgpu::Color>,
+    region: Option<[u32; 4]>,
+  ) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+      label: Some("Checker Pass"),
+      color_attachments: &[
+        wgpu::RenderPassColorAttachment {
+          view,
+          resolve_target: None,
+          ops: wgpu::Operations {
+            load,
+            store: true,
+          },
+        }
+      ],
+      depth_stencil_attachment: None,
+    });
+
+    render_pass.set_pipeline(&self.render_pipeline);
+    if let Some([x, y, width, height]) = region {
+      render_pass.set_scissor_rect(x, y, width, height);
+    }
+    render_pass.draw(0..3, 0..1);
+  }
+}
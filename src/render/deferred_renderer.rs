@@ -0,0 +1,271 @@
+use crate::{
+  draw::DrawModel,
+  model::Model,
+  render::{create_render_pipeline, PipelineOptions},
+  texture::Texture,
+};
+
+const ALBEDO_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+// Float so emission can go past one
+const EMISSIVE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const NORMAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const POSITION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+// Surface attributes for one window size. A position with w of zero marks a pixel
+// no geometry was drawn to.
+pub struct GBuffer {
+  albedo: Texture,
+  bind_group: wgpu::BindGroup,
+  emissive: Texture,
+  normal: Texture,
+  position: Texture,
+}
+
+pub struct DeferredRenderer {
+  bind_group_layout: wgpu::BindGroupLayout,
+  gbuffer_pipeline: wgpu::RenderPipeline,
+  lighting_pipeline: wgpu::RenderPipeline,
+}
+
+impl DeferredRenderer {
+  // The model layouts are shading, camera, light and mesh, in group order
+  pub fn new(
+    device: &wgpu::Device,
+    model_layouts: [&wgpu::BindGroupLayout; 4],
+    format: wgpu::TextureFormat,
+    depth_format: Option<wgpu::TextureFormat>,
+    vertex_layouts: &[wgpu::VertexBufferLayout],
+  ) -> Self {
+    let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+      binding,
+      visibility: wgpu::ShaderStages::FRAGMENT,
+      ty: wgpu::BindingType::Texture {
+        multisampled: false,
+        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+        view_dimension: wgpu::TextureViewDimension::D2,
+      },
+      count: None,
+    };
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      entries: &[texture_entry(1), texture_entry(2), texture_entry(3), texture_entry(4)],
+      label: Some("gbuffer_bind_group_layout"),
+    });
+    let shader = || wgpu::ShaderModuleDescriptor {
+      label: Some("Deferred Shader"),
+      source: wgpu::ShaderSource::Wgsl(
+        concat!(
+          include_str!("shaders/fullscreen.wgsl"),
+          include_str!("shaders/mesh.wgsl"),
+          include_str!("shaders/shader.wgsl"),
+          include_str!("shaders/deferred.wgsl"),
+        ).into()
+      ),
+    };
+
+    let gbuffer_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("G-Buffer Pipeline Layout"),
+      bind_group_layouts: &model_layouts,
+      push_constant_ranges: &[],
+    });
+    let gbuffer_shader = device.create_shader_module(&shader());
+    // Unblended, Rgba32Float positions can't be blended without a device feature
+    let gbuffer_target = |format| wgpu::ColorTargetState {
+      format,
+      blend: None,
+      write_mask: wgpu::ColorWrites::ALL,
+    };
+    // Written out by hand as create_render_pipeline only knows a single color target
+    let gbuffer_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+      label: Some("G-Buffer Render Pipeline"),
+      layout: Some(&gbuffer_pipeline_layout),
+      vertex: wgpu::VertexState {
+        module: &gbuffer_shader,
+        entry_point: "vs_main",
+        buffers: vertex_layouts,
+      },
+      fragment: Some(wgpu::FragmentState {
+        module: &gbuffer_shader,
+        entry_point: "fs_gbuffer",
+        targets: &[
+          gbuffer_target(POSITION_FORMAT),
+          gbuffer_target(NORMAL_FORMAT),
+          gbuffer_target(ALBEDO_FORMAT),
+          gbuffer_target(EMISSIVE_FORMAT),
+        ],
+      }),
+      primitive: wgpu::PrimitiveState {
+        topology: wgpu::PrimitiveTopology::TriangleList,
+        strip_index_format: None,
+        front_face: wgpu::FrontFace::Ccw,
+        cull_mode: Some(wgpu::Face::Back),
+        polygon_mode: wgpu::PolygonMode::Fill,
+        unclipped_depth: false,
+        conservative: false,
+      },
+      depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+        format,
+        depth_write_enabled: true,
+        depth_compare: wgpu::CompareFunction::Less,
+        stencil: wgpu::StencilState::default(),
+        bias: wgpu::DepthBiasState::default(),
+      }),
+      multisample: wgpu::MultisampleState {
+        count: 1,
+        mask: !0,
+        alpha_to_coverage_enabled: false,
+      },
+      multiview: None,
+    });
+
+    let [shading_layout, camera_layout, light_layout, _] = model_layouts;
+    let lighting_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("Lighting Pipeline Layout"),
+      bind_group_layouts: &[shading_layout, camera_layout, light_layout, &bind_group_layout],
+      push_constant_ranges: &[],
+    });
+    let lighting_pipeline = create_render_pipeline(
+      device,
+      &lighting_pipeline_layout,
+      format,
+      None,
+      &[],
+      shader(),
+      PipelineOptions {
+        cull_mode: None,
+        fragment_entry: "fs_lighting",
+        vertex_entry: "vs_fullscreen",
+        ..PipelineOptions::new("Lighting Render Pipeline")
+      },
+    );
+
+    Self {
+      bind_group_layout,
+      gbuffer_pipeline,
+      lighting_pipeline,
+    }
+  }
+
+  pub fn create_gbuffer(&self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> GBuffer {
+    let position = Texture::create_render_target(device, config, POSITION_FORMAT, "gbuffer_position");
+    let normal = Texture::create_render_target(device, config, NORMAL_FORMAT, "gbuffer_normal");
+    let albedo = Texture::create_render_target(device, config, ALBEDO_FORMAT, "gbuffer_albedo");
+    let emissive = Texture::create_render_target(device, config, EMISSIVE_FORMAT, "gbuffer_emissive");
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+      layout: &self.bind_group_layout,
+      entries: &[
+        wgpu::BindGroupEntry {
+          binding: 1,
+          resource: wgpu::BindingResource::TextureView(&position.view),
+        },
+        wgpu::BindGroupEntry {
+          binding: 2,
+          resource: wgpu::BindingResource::TextureView(&normal.view),
+        },
+        wgpu::BindGroupEntry {
+          binding: 3,
+          resource: wgpu::BindingResource::TextureView(&albedo.view),
+        },
+        wgpu::BindGroupEntry {
+          binding: 4,
+          resource: wgpu::BindingResource::TextureView(&emissive.view),
+        },
+      ],
+      label: Some("gbuffer_bind_group"),
+    });
+
+    GBuffer {
+      albedo,
+      bind_group,
+      emissive,
+      normal,
+      position,
+    }
+  }
+
+  // Fills the G-buffer and depth from the models, then lights every covered pixel of the target
+  // in one fullscreen pass. Pixels without geometry keep what color_load leaves there. Geometry is
+  // drawn into the [x, y, width, height] viewport.
+  #[allow(clippy::too_many_arguments)]
+  pub fn render(
+    &self,
+    encoder: &mut wgpu::CommandEncoder,
+    gbuffer: &GBuffer,
+    target: &wgpu::TextureView,
+    color_load: wgpu::LoadOp<wgpu::Color>,
+    depth_view: Option<&wgpu::TextureView>,
+    depth_load: wgpu::LoadOp<f32>,
+    viewport: [u32; 4],
+    models: &[Model],
+    shading_bind_group: &wgpu::BindGroup,
+    camera_bind_group: &wgpu::BindGroup,
+    light_bind_group: &wgpu::BindGroup,
+  ) {
+    let gbuffer_attachment = |texture: &Texture| wgpu::RenderPassColorAttachment {
+      view: &texture.view,
+      resolve_target: None,
+      ops: wgpu::Operations {
+        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+        store: true,
+      },
+    };
+
+    {
+      let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("G-Buffer Pass"),
+        color_attachments: &[
+          gbuffer_attachment(&gbuffer.position),
+          gbuffer_attachment(&gbuffer.normal),
+          gbuffer_attachment(&gbuffer.albedo),
+          gbuffer_attachment(&gbuffer.emissive),
+        ],
+        depth_stencil_attachment: depth_view.map(|view| wgpu::RenderPassDepthStencilAttachment {
+          view,
+          depth_ops: Some(wgpu::Operations {
+            load: depth_load,
+            store: true,
+          }),
+          stencil_ops: None,
+        }),
+      });
+
+      let [x, y, width, height] = viewport;
+
+      render_pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+      render_pass.set_scissor_rect(x, y, width, height);
+      render_pass.set_pipeline(&self.gbuffer_pipeline);
+      for model in models {
+        render_pass.set_vertex_buffer(1, model.instances.buffer().slice(..));
+        render_pass.draw_model_instanced(
+          model,
+          model.instances.range(),
+          shading_bind_group,
+          camera_bind_group,
+          light_bind_group,
+        );
+      }
+    }
+    {
+      let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Lighting Pass"),
+        color_attachments: &[
+          wgpu::RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: wgpu::Operations {
+              load: color_load,
+              store: true,
+            },
+          }
+        ],
+        depth_stencil_attachment: None,
+      });
+
+      render_pass.set_pipeline(&self.lighting_pipeline);
+      render_pass.set_bind_group(0, shading_bind_group, &[]);
+      render_pass.set_bind_group(1, camera_bind_group, &[]);
+      render_pass.set_bind_group(2, light_bind_group, &[]);
+      render_pass.set_bind_group(3, &gbuffer.bind_group, &[]);
+      render_pass.draw(0..3, 0..1);
+    }
+  }
+}
@@ -0,0 +1,71 @@
+use crate::render::{create_render_pipeline, PipelineOptions};
+
+// How the ground grid is drawn, when it is drawn at all
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Grid {
+  // Unit line list geometry, limited to a fixed extent
+  Lines,
+  // Worked out per pixel over the whole ground plane, antialiased and fading with distance
+  Shader,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GridUniform {
+  pub inverse_view_proj: [[f32; 4]; 4],
+}
+
+// Draws an infinite y = 0 grid with a fullscreen triangle, blended over what is already there
+// and depth tested at the ground so models hide it
+pub struct GridRenderer {
+  render_pipeline: wgpu::RenderPipeline,
+}
+
+impl GridRenderer {
+  pub fn new(
+    device: &wgpu::Device,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+    depth_format: Option<wgpu::TextureFormat>,
+  ) -> Self {
+    let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("Grid Pipeline Layout"),
+      bind_group_layouts,
+      push_constant_ranges: &[],
+    });
+    let shader = wgpu::ShaderModuleDescriptor {
+      label: Some("Grid Shader"),
+      source: wgpu::ShaderSource::Wgsl(
+        concat!(include_str!("shaders/fullscreen.wgsl"), include_str!("shaders/grid.wgsl")).into()
+      ),
+    };
+    let render_pipeline = create_render_pipeline(
+      device,
+      &render_pipeline_layout,
+      format,
+      depth_format,
+      &[],
+      shader,
+      PipelineOptions {
+        blend: wgpu::BlendState::ALPHA_BLENDING,
+        cull_mode: None,
+        depth_write: false,
+        ..PipelineOptions::new("Grid Render Pipeline")
+      },
+    );
+
+    Self { render_pipeline }
+  }
+
+  pub fn render<'a>(
+    &'a self,
+    render_pass: &mut wgpu::RenderPass<'a>,
+    camera_bind_group: &'a wgpu::BindGroup,
+    grid_bind_group: &'a wgpu::BindGroup,
+  ) {
+    render_pass.set_pipeline(&self.render_pipeline);
+    render_pass.set_bind_group(0, camera_bind_group, &[]);
+    render_pass.set_bind_group(1, grid_bind_group, &[]);
+    render_pass.draw(0..3, 0..1);
+  }
+}
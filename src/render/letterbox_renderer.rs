@@ -0,0 +1,68 @@
+use crate::render::{create_render_pipeline, PipelineOptions};
+
+// Paints the parts of the target outside a locked aspect viewport black
+pub struct LetterboxRenderer {
+  render_pipeline: wgpu::RenderPipeline,
+}
+
+impl LetterboxRenderer {
+  pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+    let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("Letterbox Pipeline Layout"),
+      bind_group_layouts: &[],
+      push_constant_ranges: &[],
+    });
+    let shader = wgpu::ShaderModuleDescriptor {
+      label: Some("Letterbox Shader"),
+      source: wgpu::ShaderSource::Wgsl(
+        concat!(include_str!("shaders/fullscreen.wgsl"), include_str!("shaders/letterbox.wgsl")).into()
+      ),
+    };
+    let render_pipeline = create_render_pipeline(
+      device,
+      &render_pipeline_layout,
+      format,
+      None,
+      &[],
+      shader,
+      PipelineOptions {
+        vertex_entry: "vs_fullscreen",
+        ..PipelineOptions::new("Letterbox Render Pipeline")
+      },
+    );
+
+    Self { render_pipeline }
+  }
+
+  // Fills the target around the [x, y, width, height] viewport, keeping what is inside it
+  pub fn render(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, size: [u32; 2], viewport: [u32; 4]) {
+    let [width, height] = size;
+    let [x, y, viewport_width, viewport_height] = viewport;
+    let bars = [
+      [0, 0, x, height],
+      [x + viewport_width, 0, width - x - viewport_width, height],
+      [0, 0, width, y],
+      [0, y + viewport_height, width, height - y - viewport_height],
+    ];
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+      label: Some("Letterbox Pass"),
+      color_attachments: &[
+        wgpu::RenderPassColorAttachment {
+          view,
+          resolve_target: None,
+          ops: wgpu::Operations {
+            load: wgpu::LoadOp::Load,
+            store: true,
+          },
+        }
+      ],
+      depth_stencil_attachment: None,
+    });
+
+    render_pass.set_pipeline(&self.render_pipeline);
+    for [x, y, width, height] in bars.into_iter().filter(|bar| bar[2] > 0 && bar[3] > 0) {
+      render_pass.set_scissor_rect(x, y, width, height);
+      render_pass.draw(0..3, 0..1);
+    }
+  }
+}
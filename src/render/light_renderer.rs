@@ -1,6 +1,6 @@
 use crate::{
   draw::DrawLight,
-  render::create_render_pipeline,
+  render::{create_render_pipeline, PipelineOptions},
 };
 
 pub struct LightRenderer {
@@ -34,7 +34,7 @@ impl LightRenderer {
         depth_format,
         vertex_layouts,
         shader,
-        "Light Render Pipeline",
+        PipelineOptions::new("Light Render Pipeline"),
       )
     };
 
@@ -49,6 +49,7 @@ impl LightRenderer {
     light_bind_group: &'a wgpu::BindGroup,
   ) {
     render_pass.set_pipeline(&self.render_pipeline);
+    render_pass.set_vertex_buffer(1, model.instances.buffer().slice(..));
     render_pass.draw_light_model(
       model,
       camera_bind_group,
@@ -0,0 +1,75 @@
+use std::ops::Range;
+
+use crate::{
+  mesh::Mesh,
+  render::{create_render_pipeline, PipelineOptions},
+};
+
+// Draws line list meshes with flat vertex colors, placed by their mesh uniform and the instance transforms
+pub struct LineRenderer {
+  render_pipeline: wgpu::RenderPipeline,
+}
+
+impl LineRenderer {
+  pub fn new(
+    device: &wgpu::Device,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+    depth_format: Option<wgpu::TextureFormat>,
+    vertex_layouts: &[wgpu::VertexBufferLayout],
+  ) -> Self {
+    let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("Line Pipeline Layout"),
+      bind_group_layouts,
+      push_constant_ranges: &[],
+    });
+    let render_pipeline = {
+      let shader = wgpu::ShaderModuleDescriptor {
+        label: Some("Line Shader"),
+        source: wgpu::ShaderSource::Wgsl(
+          concat!(include_str!("shaders/mesh.wgsl"), include_str!("shaders/line.wgsl")).into()
+        ),
+      };
+
+      create_render_pipeline(
+        device,
+        &render_pipeline_layout,
+        format,
+        depth_format,
+        vertex_layouts,
+        shader,
+        PipelineOptions {
+          // Equal depth lets edges win over the faces they were taken from
+          depth_compare: wgpu::CompareFunction::LessEqual,
+          topology: wgpu::PrimitiveTopology::LineList,
+          ..PipelineOptions::new("Line Render Pipeline")
+        },
+      )
+    };
+
+    Self { render_pipeline }
+  }
+
+  pub fn render<'a, M>(
+    &'a self,
+    render_pass: &mut wgpu::RenderPass<'a>,
+    meshes: M,
+    instance_buffer: &'a wgpu::Buffer,
+    instances: Range<u32>,
+    camera_bind_group: &'a wgpu::BindGroup,
+  )
+  where
+    M: Iterator<Item = &'a Mesh>,
+  {
+    render_pass.set_pipeline(&self.render_pipeline);
+    render_pass.set_bind_group(0, camera_bind_group, &[]);
+    render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+
+    for mesh in meshes {
+      render_pass.set_bind_group(1, &mesh.uniform.bind_group, &[]);
+      render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+      render_pass.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format);
+      render_pass.draw_indexed(0..mesh.num_elements, 0, instances.clone());
+    }
+  }
+}
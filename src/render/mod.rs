@@ -1,10 +1,73 @@
+pub mod accumulate_renderer;
+pub mod blit_renderer;
+pub mod checker_renderer;
+pub mod deferred_renderer;
+pub mod grid_renderer;
+pub mod letterbox_renderer;
 pub mod light_renderer;
+pub mod line_renderer;
 pub mod model_renderer;
+pub mod point_renderer;
 pub mod renderer;
+pub mod wire_renderer;
 
+pub use accumulate_renderer::AccumulateRenderer;
+pub use blit_renderer::BlitRenderer;
+pub use checker_renderer::CheckerRenderer;
+pub use deferred_renderer::DeferredRenderer;
+pub use grid_renderer::{Grid, GridRenderer};
+pub use letterbox_renderer::LetterboxRenderer;
 pub use light_renderer::LightRenderer;
+pub use line_renderer::LineRenderer;
 pub use model_renderer::ModelRenderer;
+pub use point_renderer::PointRenderer;
 pub use renderer::Renderer;
+pub use wire_renderer::WireRenderer;
+
+use crate::texture::Texture;
+
+// Options that are fixed once the renderer has been created
+#[derive(Clone, Copy, Debug)]
+pub struct RenderOptions {
+    pub deferred: bool,
+    // None draws without a depth buffer, in submission order
+    pub depth_format: Option<wgpu::TextureFormat>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            deferred: false,
+            depth_format: Some(Texture::DEPTH_FORMAT),
+        }
+    }
+}
+
+pub struct PipelineOptions<'a> {
+    pub blend: wgpu::BlendState,
+    pub cull_mode: Option<wgpu::Face>,
+    pub depth_compare: wgpu::CompareFunction,
+    pub depth_write: bool,
+    pub fragment_entry: &'a str,
+    pub label: &'a str,
+    pub topology: wgpu::PrimitiveTopology,
+    pub vertex_entry: &'a str,
+}
+
+impl<'a> PipelineOptions<'a> {
+    pub fn new(label: &'a str) -> Self {
+        Self {
+            blend: wgpu::BlendState::REPLACE,
+            cull_mode: Some(wgpu::Face::Back),
+            depth_compare: wgpu::CompareFunction::Less,
+            depth_write: true,
+            fragment_entry: "fs_main",
+            label,
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            vertex_entry: "vs_main",
+        }
+    }
+}
 
 pub fn create_render_pipeline(
     device: &wgpu::Device,
@@ -13,43 +76,40 @@ pub fn create_render_pipeline(
     depth_format: Option<wgpu::TextureFormat>,
     vertex_layouts: &[wgpu::VertexBufferLayout],
     shader: wgpu::ShaderModuleDescriptor,
-    label: &str,
+    options: PipelineOptions,
 ) -> wgpu::RenderPipeline {
     let shader = device.create_shader_module(&shader);
 
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some(label),
+        label: Some(options.label),
         layout: Some(layout),
         vertex: wgpu::VertexState {
             module: &shader,
-            entry_point: "vs_main",
+            entry_point: options.vertex_entry,
             buffers: vertex_layouts,
         },
         fragment: Some(wgpu::FragmentState {
             module: &shader,
-            entry_point: "fs_main",
+            entry_point: options.fragment_entry,
             targets: &[wgpu::ColorTargetState {
                 format: color_format,
-                blend: Some(wgpu::BlendState{
-                    alpha: wgpu::BlendComponent::REPLACE,
-                    color: wgpu::BlendComponent::REPLACE,
-                }),
+                blend: Some(options.blend),
                 write_mask: wgpu::ColorWrites::ALL,
             }],
         }),
         primitive: wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
+            topology: options.topology,
             strip_index_format: None,
             front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
+            cull_mode: options.cull_mode,
             polygon_mode: wgpu::PolygonMode::Fill,
             unclipped_depth: false,
             conservative: false,
         },
         depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
             format,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
+            depth_write_enabled: options.depth_write,
+            depth_compare: options.depth_compare,
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default(),
         }),
@@ -1,10 +1,12 @@
+use std::ops::Range;
+
 use crate::{
   draw::DrawModel,
-  render::create_render_pipeline,
+  render::{create_render_pipeline, PipelineOptions},
+  shading::ShadingModel,
 };
 
 pub struct ModelRenderer {
-  num_instances: u32,
   render_pipeline: wgpu::RenderPipeline,
 }
 
@@ -15,6 +17,28 @@ impl ModelRenderer {
     format: wgpu::TextureFormat,
     depth_format: Option<wgpu::TextureFormat>,
     vertex_layouts: &[wgpu::VertexBufferLayout],
+    shading_model: ShadingModel,
+  ) -> Self {
+    Self::with_options(
+      device,
+      bind_group_layouts,
+      format,
+      depth_format,
+      vertex_layouts,
+      PipelineOptions {
+        fragment_entry: shading_model.fragment_entry(),
+        ..PipelineOptions::new("Render Pipeline")
+      },
+    )
+  }
+
+  pub fn with_options(
+    device: &wgpu::Device,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+    depth_format: Option<wgpu::TextureFormat>,
+    vertex_layouts: &[wgpu::VertexBufferLayout],
+    options: PipelineOptions,
   ) -> Self {
     let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
       label: Some("render Pipeline Layout"),
@@ -24,7 +48,9 @@ impl ModelRenderer {
     let render_pipeline = {
       let shader = wgpu::ShaderModuleDescriptor {
         label: Some("Normal Shader"),
-        source: wgpu::ShaderSource::Wgsl(include_str!("shaders/shader.wgsl").into()),
+        source: wgpu::ShaderSource::Wgsl(
+          concat!(include_str!("shaders/mesh.wgsl"), include_str!("shaders/shader.wgsl")).into()
+        ),
       };
 
       create_render_pipeline(
@@ -34,29 +60,46 @@ impl ModelRenderer {
         depth_format,
         vertex_layouts,
         shader,
-        "Render Pipeline",
+        options,
       )
     };
 
-    Self {
-      num_instances: 1,
-      render_pipeline,
-    }
+    Self { render_pipeline }
   }
 
   pub fn render<'a>(
     &'a self,
     render_pass: &mut wgpu::RenderPass<'a>,
     model: &'a crate::model::Model,
-    ambient_bind_group: &'a wgpu::BindGroup,
+    shading_bind_group: &'a wgpu::BindGroup,
+    camera_bind_group: &'a wgpu::BindGroup,
+    light_bind_group: &'a wgpu::BindGroup,
+  ) {
+    self.render_instances(
+      render_pass,
+      model,
+      model.instances.range(),
+      shading_bind_group,
+      camera_bind_group,
+      light_bind_group,
+    );
+  }
+
+  pub fn render_instances<'a>(
+    &'a self,
+    render_pass: &mut wgpu::RenderPass<'a>,
+    model: &'a crate::model::Model,
+    instances: Range<u32>,
+    shading_bind_group: &'a wgpu::BindGroup,
     camera_bind_group: &'a wgpu::BindGroup,
     light_bind_group: &'a wgpu::BindGroup,
   ) {
     render_pass.set_pipeline(&self.render_pipeline);
+    render_pass.set_vertex_buffer(1, model.instances.buffer().slice(..));
     render_pass.draw_model_instanced(
       model,
-      0..self.num_instances,
-      ambient_bind_group,
+      instances,
+      shading_bind_group,
       camera_bind_group,
       light_bind_group,
     );
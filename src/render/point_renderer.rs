@@ -0,0 +1,101 @@
+use std::ops::Range;
+
+use crate::{
+  instance::InstanceRaw,
+  mesh::Mesh,
+  render::{create_render_pipeline, PipelineOptions},
+};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointUniform {
+  pub size: f32,
+  pub _size_padding: u32,
+  pub viewport: [f32; 2],
+}
+
+// Each point is drawn as an instance of a six vertex quad expanded in the vertex shader.
+// The model transform is read through a zero stride slice of the instance buffer so
+// every model instance gets its own draw.
+pub struct PointRenderer {
+  render_pipeline: wgpu::RenderPipeline,
+}
+
+impl PointRenderer {
+  pub fn new(
+    device: &wgpu::Device,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+    depth_format: Option<wgpu::TextureFormat>,
+    vertex_layouts: &[wgpu::VertexBufferLayout],
+  ) -> Self {
+    let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("Point Pipeline Layout"),
+      bind_group_layouts,
+      push_constant_ranges: &[],
+    });
+    let [point_layout, instance_layout] = [vertex_layouts[0].clone(), vertex_layouts[1].clone()];
+    let vertex_layouts = [
+      wgpu::VertexBufferLayout {
+        step_mode: wgpu::VertexStepMode::Instance,
+        ..point_layout
+      },
+      wgpu::VertexBufferLayout {
+        array_stride: 0,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        ..instance_layout
+      },
+    ];
+    let render_pipeline = {
+      let shader = wgpu::ShaderModuleDescriptor {
+        label: Some("Point Shader"),
+        source: wgpu::ShaderSource::Wgsl(
+          concat!(include_str!("shaders/mesh.wgsl"), include_str!("shaders/point.wgsl")).into()
+        ),
+      };
+
+      create_render_pipeline(
+        device,
+        &render_pipeline_layout,
+        format,
+        depth_format,
+        &vertex_layouts,
+        shader,
+        PipelineOptions::new("Point Render Pipeline"),
+      )
+    };
+
+    Self { render_pipeline }
+  }
+
+  pub fn render<'a, M>(
+    &'a self,
+    render_pass: &mut wgpu::RenderPass<'a>,
+    meshes: M,
+    instance_buffer: &'a wgpu::Buffer,
+    instances: Range<u32>,
+    camera_bind_group: &'a wgpu::BindGroup,
+    point_bind_group: &'a wgpu::BindGroup,
+  )
+  where
+    M: Iterator<Item = &'a Mesh>,
+  {
+    let instance_size = std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress;
+
+    render_pass.set_pipeline(&self.render_pipeline);
+    render_pass.set_bind_group(0, camera_bind_group, &[]);
+    render_pass.set_bind_group(1, point_bind_group, &[]);
+
+    for mesh in meshes {
+      render_pass.set_bind_group(2, &mesh.uniform.bind_group, &[]);
+      render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+
+      for instance in instances.clone() {
+        let offset = instance as wgpu::BufferAddress * instance_size;
+
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(offset..offset + instance_size));
+        render_pass.draw(0..6, 0..mesh.num_vertices);
+      }
+    }
+  }
+}
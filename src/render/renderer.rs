@@ -1,42 +1,158 @@
 use cgmath::{
+    ortho,
     Deg,
+    EuclideanSpace,
+    InnerSpace,
+    Matrix4,
+    Point3,
     Quaternion,
     Rotation3,
+    SquareMatrix,
+    Vector2,
     Vector3,
+    Vector4,
+    Zero,
 };
 
 use crate::{
+  bounds::Aabb,
   camera::{Camera, CameraUniform},
   color::ColorUniform,
-  instance::InstanceRaw,
+  instance::{Instance, InstanceBuffer, InstanceRaw},
   light::LightUniform,
-  mesh::{MeshVertex, Vertex},
+  mesh::{Mesh, MeshBuilder, MeshVertex, Vertex},
   model::Model,
-  projection::Projection,
-  render::{LightRenderer, ModelRenderer},
+  projection::{Projection, OPENGL_TO_WGPU_MATRIX},
+  render::{
+    accumulate_renderer::Accumulation,
+    blit_renderer::FrameTarget,
+    deferred_renderer::GBuffer,
+    grid_renderer::GridUniform,
+    point_renderer::PointUniform,
+    wire_renderer::WireUniform,
+    AccumulateRenderer,
+    BlitRenderer,
+    CheckerRenderer,
+    DeferredRenderer,
+    Grid,
+    GridRenderer,
+    LetterboxRenderer,
+    LightRenderer,
+    LineRenderer,
+    ModelRenderer,
+    PipelineOptions,
+    PointRenderer,
+    RenderOptions,
+    WireRenderer,
+  },
+  shading::{ShadingModel, ShadingUniform},
   texture::Texture,
-  uniform::Uniform,
+  ui::Ui,
+  uniform::{self, Uniform},
 };
 
+const AXES_DISTANCE: f32 = 3.0;
+// Stops either way, well past anything useful
+const EXPOSURE_LIMIT: f32 = 8.0;
+const FOOTPRINT_COLOR: [f32; 4] = [1.0, 0.9, 0.1, 1.0];
+const FRUSTUM_COLOR: [f32; 4] = [0.2, 0.9, 1.0, 1.0];
+const GRID_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+// Lines of the line geometry grid run from -GRID_EXTENT to GRID_EXTENT on both axes
+const GRID_EXTENT: i32 = 10;
+const MINIMAP_MARGIN: u32 = 16;
+
+pub struct PipelineReport {
+  pub label: &'static str,
+  pub error: Option<wgpu::Error>,
+}
+
 pub struct Renderer {
-  ambient_uniform: Uniform<ColorUniform>,
+  accumulate_renderer: AccumulateRenderer,
+  accumulation: Option<Accumulation>,
+  axes_camera: Uniform<CameraUniform>,
+  axes_mesh: Mesh,
+  background: [f32; 3],
+  blit_renderer: BlitRenderer,
   camera_uniform: Uniform<CameraUniform>,
-  depth_texture: Texture,
+  checker_background: bool,
+  checker_renderer: CheckerRenderer,
+  clear_color: bool,
+  clear_depth: bool,
+  cull_backfaces: bool,
+  deferred_renderer: DeferredRenderer,
+  depth_format: Option<wgpu::TextureFormat>,
+  depth_texture: Option<Texture>,
+  // Phong and PBR with culling off, swapped in while cull_backfaces is unset
+  double_sided_pbr_renderer: ModelRenderer,
+  double_sided_renderer: ModelRenderer,
+  facing_renderer: ModelRenderer,
+  frame_target: Option<FrameTarget>,
+  // Outline of the view volume at the moment it was frozen, left in place as the camera moves on
+  frozen_frustum: Option<Mesh>,
+  gbuffer: Option<GBuffer>,
+  // Debug groups and markers for graphics debuggers, left out otherwise
+  gpu_debug: bool,
+  grid: Option<Grid>,
+  grid_mesh: Option<Mesh>,
+  grid_renderer: GridRenderer,
+  grid_uniform: Uniform<GridUniform>,
+  headlight: bool,
+  instance_renderer: ModelRenderer,
   light_renderer: LightRenderer,
+  last_view_proj: Matrix4<f32>,
+  letterbox_renderer: LetterboxRenderer,
+  light_orbit_center: Vector3<f32>,
+  light_orbit_speed: Deg<f32>,
   light_uniform: Uniform<LightUniform>,
+  line_renderer: LineRenderer,
+  minimap_camera: Uniform<CameraUniform>,
+  minimap_footprint: Vec<Point3<f32>>,
+  // Outline of minimap_footprint, rewritten each frame the minimap is drawn
+  minimap_footprint_mesh: Mesh,
   model_renderer: ModelRenderer,
+  needs_clear: bool,
+  outline_renderer: ModelRenderer,
+  pbr_renderer: ModelRenderer,
+  pipeline_reports: Vec<PipelineReport>,
+  point_renderer: PointRenderer,
+  point_uniform: Uniform<PointUniform>,
   projection: Projection,
+  render_axes: bool,
+  render_bounds: bool,
+  render_facing: bool,
+  render_instance_colors: bool,
   render_light: bool,
+  render_minimap: bool,
+  render_normals: bool,
+  render_points: bool,
+  render_wireframe: bool,
+  render_xray: bool,
   rotate_light: bool,
+  selected_instance: Option<usize>,
+  shading_model: ShadingModel,
+  shading_uniform: Uniform<ShadingUniform>,
+  size: [u32; 2],
+  split_shading_model: Option<ShadingModel>,
+  transparent: bool,
+  wire_renderer: WireRenderer,
+  wire_uniform: Uniform<WireUniform>,
+  // Pixel width of wireframe edges, or None for native one pixel lines
+  wire_width: Option<f32>,
+  // A single identity instance, for line meshes already in world space
+  world_instances: InstanceBuffer,
+  xray_renderer: ModelRenderer,
 }
 
 impl Renderer {
   pub fn new(
     device: &wgpu::Device,
     config: &wgpu::SurfaceConfiguration,
+    options: &RenderOptions,
   ) -> Self {
     let camera_uniform = Uniform::new(device, CameraUniform::new(), "camera");
-    let ambient_uniform = Uniform::new(device, ColorUniform { color: [0.3, 0.3, 0.3, 1.0] }, "ambient");
+    let minimap_camera = Uniform::new(device, CameraUniform::new(), "minimap_camera");
+    let axes_camera = Uniform::new(device, CameraUniform::new(), "axes_camera");
+    let shading_uniform = Uniform::new(device, ShadingUniform::default(), "shading");
     let light_uniform = Uniform::new(
       device,
       LightUniform {
@@ -47,11 +163,39 @@ impl Renderer {
       },
       "light",
     );
+    let point_uniform = Uniform::new(
+      device,
+      PointUniform {
+        size: 4.0,
+        _size_padding: 0,
+        viewport: [config.width as f32, config.height as f32],
+      },
+      "point",
+    );
+    let wire_uniform = Uniform::new(
+      device,
+      WireUniform {
+        width: 1.0,
+        _width_padding: 0,
+        viewport: [config.width as f32, config.height as f32],
+      },
+      "wire",
+    );
+    let grid_uniform = Uniform::new(
+      device,
+      GridUniform {
+        inverse_view_proj: Matrix4::identity().into(),
+      },
+      "grid",
+    );
+
+    let mesh_bind_group_layout = uniform::create_bind_group_layout(device, "mesh");
 
-    let depth_format = Some(Texture::DEPTH_FORMAT);
-    let depth_texture = Texture::create_depth_texture(device, config, "depth_texture");
+    let depth_format = options.depth_format;
+    let depth_texture = depth_format.map(|format| Texture::create_depth_texture(device, config, format, "depth_texture"));
     let vertex_layouts = [MeshVertex::desc(), InstanceRaw::desc()];
-    let light_renderer = LightRenderer::new(
+    let mut pipeline_reports = Vec::new();
+    let light_renderer = build_pipeline(device, &mut pipeline_reports, "Light", || LightRenderer::new(
       device,
       &[
         &camera_uniform.bind_group_layout,
@@ -60,31 +204,645 @@ impl Renderer {
       config.format,
       depth_format,
       &vertex_layouts,
-    );
-    let model_renderer = ModelRenderer::new(
+    ));
+    let grid_renderer = build_pipeline(device, &mut pipeline_reports, "Grid", || GridRenderer::new(
       device,
       &[
-        &ambient_uniform.bind_group_layout,
+        &camera_uniform.bind_group_layout,
+        &grid_uniform.bind_group_layout,
+      ],
+      config.format,
+      depth_format,
+    ));
+    let line_renderer = build_pipeline(device, &mut pipeline_reports, "Line", || LineRenderer::new(
+      device,
+      &[&camera_uniform.bind_group_layout, &mesh_bind_group_layout],
+      config.format,
+      depth_format,
+      &vertex_layouts,
+    ));
+    let model_renderer = build_pipeline(device, &mut pipeline_reports, "Phong", || ModelRenderer::new(
+      device,
+      &[
+        &shading_uniform.bind_group_layout,
         &camera_uniform.bind_group_layout,
         &light_uniform.bind_group_layout,
+        &mesh_bind_group_layout,
       ],
       config.format,
       depth_format,
       &vertex_layouts,
-    );
+      ShadingModel::Phong,
+    ));
+    let pbr_renderer = build_pipeline(device, &mut pipeline_reports, "PBR", || ModelRenderer::new(
+      device,
+      &[
+        &shading_uniform.bind_group_layout,
+        &camera_uniform.bind_group_layout,
+        &light_uniform.bind_group_layout,
+        &mesh_bind_group_layout,
+      ],
+      config.format,
+      depth_format,
+      &vertex_layouts,
+      ShadingModel::Pbr,
+    ));
+    let double_sided_renderer = build_pipeline(device, &mut pipeline_reports, "Phong Double Sided", || ModelRenderer::with_options(
+      device,
+      &[
+        &shading_uniform.bind_group_layout,
+        &camera_uniform.bind_group_layout,
+        &light_uniform.bind_group_layout,
+        &mesh_bind_group_layout,
+      ],
+      config.format,
+      depth_format,
+      &vertex_layouts,
+      PipelineOptions {
+        cull_mode: None,
+        fragment_entry: ShadingModel::Phong.fragment_entry(),
+        ..PipelineOptions::new("Double Sided Render Pipeline")
+      },
+    ));
+    let double_sided_pbr_renderer = build_pipeline(device, &mut pipeline_reports, "PBR Double Sided", || ModelRenderer::with_options(
+      device,
+      &[
+        &shading_uniform.bind_group_layout,
+        &camera_uniform.bind_group_layout,
+        &light_uniform.bind_group_layout,
+        &mesh_bind_group_layout,
+      ],
+      config.format,
+      depth_format,
+      &vertex_layouts,
+      PipelineOptions {
+        cull_mode: None,
+        fragment_entry: ShadingModel::Pbr.fragment_entry(),
+        ..PipelineOptions::new("Double Sided PBR Render Pipeline")
+      },
+    ));
+    // Draws only what is hidden behind the depth buffer, blended over the scene
+    let xray_renderer = build_pipeline(device, &mut pipeline_reports, "X-Ray", || ModelRenderer::with_options(
+      device,
+      &[
+        &shading_uniform.bind_group_layout,
+        &camera_uniform.bind_group_layout,
+        &light_uniform.bind_group_layout,
+        &mesh_bind_group_layout,
+      ],
+      config.format,
+      depth_format,
+      &vertex_layouts,
+      PipelineOptions {
+        blend: wgpu::BlendState::ALPHA_BLENDING,
+        depth_compare: wgpu::CompareFunction::Greater,
+        depth_write: false,
+        fragment_entry: "fs_xray",
+        ..PipelineOptions::new("X-Ray Render Pipeline")
+      },
+    ));
+    // Colors front and back faces apart, with culling off so both are drawn
+    let facing_renderer = build_pipeline(device, &mut pipeline_reports, "Facing", || ModelRenderer::with_options(
+      device,
+      &[
+        &shading_uniform.bind_group_layout,
+        &camera_uniform.bind_group_layout,
+        &light_uniform.bind_group_layout,
+        &mesh_bind_group_layout,
+      ],
+      config.format,
+      depth_format,
+      &vertex_layouts,
+      PipelineOptions {
+        cull_mode: None,
+        fragment_entry: "fs_facing",
+        ..PipelineOptions::new("Facing Render Pipeline")
+      },
+    ));
+    let instance_renderer = build_pipeline(device, &mut pipeline_reports, "Instance", || ModelRenderer::with_options(
+      device,
+      &[
+        &shading_uniform.bind_group_layout,
+        &camera_uniform.bind_group_layout,
+        &light_uniform.bind_group_layout,
+        &mesh_bind_group_layout,
+      ],
+      config.format,
+      depth_format,
+      &vertex_layouts,
+      PipelineOptions {
+        fragment_entry: "fs_instance",
+        ..PipelineOptions::new("Instance Render Pipeline")
+      },
+    ));
+    // The selected instance pushed out along its normals, front faces culled so it reads as a rim
+    let outline_renderer = build_pipeline(device, &mut pipeline_reports, "Outline", || ModelRenderer::with_options(
+      device,
+      &[
+        &shading_uniform.bind_group_layout,
+        &camera_uniform.bind_group_layout,
+        &light_uniform.bind_group_layout,
+        &mesh_bind_group_layout,
+      ],
+      config.format,
+      depth_format,
+      &vertex_layouts,
+      PipelineOptions {
+        cull_mode: Some(wgpu::Face::Front),
+        fragment_entry: "fs_outline",
+        vertex_entry: "vs_outline",
+        ..PipelineOptions::new("Outline Render Pipeline")
+      },
+    ));
+    let point_renderer = build_pipeline(device, &mut pipeline_reports, "Point", || PointRenderer::new(
+      device,
+      &[
+        &camera_uniform.bind_group_layout,
+        &point_uniform.bind_group_layout,
+        &mesh_bind_group_layout,
+      ],
+      config.format,
+      depth_format,
+      &vertex_layouts,
+    ));
+    let wire_renderer = build_pipeline(device, &mut pipeline_reports, "Wire", || WireRenderer::new(
+      device,
+      &[
+        &camera_uniform.bind_group_layout,
+        &wire_uniform.bind_group_layout,
+        &mesh_bind_group_layout,
+      ],
+      config.format,
+      depth_format,
+      &vertex_layouts,
+    ));
+    let accumulate_renderer = build_pipeline(device, &mut pipeline_reports, "Accumulate", || AccumulateRenderer::new(
+      device,
+      config.format,
+    ));
+    let checker_renderer = build_pipeline(device, &mut pipeline_reports, "Checker", || CheckerRenderer::new(
+      device,
+      config.format,
+    ));
+    let letterbox_renderer = build_pipeline(device, &mut pipeline_reports, "Letterbox", || LetterboxRenderer::new(
+      device,
+      config.format,
+    ));
+    let blit_renderer = build_pipeline(device, &mut pipeline_reports, "Blit", || BlitRenderer::new(
+      device,
+      config.format,
+    ));
+    let deferred_renderer = build_pipeline(device, &mut pipeline_reports, "Deferred", || DeferredRenderer::new(
+      device,
+      [
+        &shading_uniform.bind_group_layout,
+        &camera_uniform.bind_group_layout,
+        &light_uniform.bind_group_layout,
+        &mesh_bind_group_layout,
+      ],
+      config.format,
+      depth_format,
+      &vertex_layouts,
+    ));
+    let gbuffer = options.deferred.then(|| deferred_renderer.create_gbuffer(device, config));
     let projection = Projection::new(config.width, config.height, Deg(45.0), 0.1, 100.0);
 
     Self {
-      ambient_uniform,
+      accumulate_renderer,
+      accumulation: None,
+      axes_camera,
+      axes_mesh: axes_mesh(device),
+      background: [0.3, 0.3, 0.3],
+      blit_renderer,
       camera_uniform,
+      checker_background: false,
+      checker_renderer,
+      clear_color: true,
+      clear_depth: true,
+      cull_backfaces: true,
+      deferred_renderer,
+      depth_format,
       depth_texture,
+      double_sided_pbr_renderer,
+      double_sided_renderer,
+      facing_renderer,
+      frame_target: None,
+      frozen_frustum: None,
+      gbuffer,
+      gpu_debug: false,
+      grid: None,
+      grid_mesh: None,
+      grid_renderer,
+      grid_uniform,
+      headlight: false,
+      instance_renderer,
       light_renderer,
+      last_view_proj: Matrix4::identity(),
+      letterbox_renderer,
+      light_orbit_center: Vector3::zero(),
+      light_orbit_speed: Deg(60.0),
       light_uniform,
+      line_renderer,
+      minimap_camera,
+      minimap_footprint: Vec::new(),
+      minimap_footprint_mesh: footprint_mesh(device),
       model_renderer,
+      needs_clear: true,
+      outline_renderer,
+      pbr_renderer,
+      pipeline_reports,
+      point_renderer,
+      point_uniform,
       projection,
+      render_axes: false,
+      render_bounds: false,
+      render_facing: false,
+      render_instance_colors: false,
       render_light: false,
+      render_minimap: false,
+      render_normals: false,
+      render_points: false,
+      render_wireframe: false,
+      render_xray: false,
       rotate_light: false,
+      selected_instance: None,
+      shading_model: ShadingModel::Phong,
+      shading_uniform,
+      size: [config.width, config.height],
+      split_shading_model: None,
+      transparent: false,
+      wire_renderer,
+      wire_uniform,
+      wire_width: None,
+      world_instances: InstanceBuffer::new(device, vec![Instance::default()]),
+      xray_renderer,
+    }
+  }
+
+  pub fn ambient(&self) -> [f32; 3] {
+    let [r, g, b, _] = self.shading_uniform.uniform.ambient;
+
+    [r, g, b]
+  }
+
+  pub fn background(&self) -> [f32; 3] {
+    self.background
+  }
+
+  pub fn exposure(&self) -> f32 {
+    self.shading_uniform.uniform.exposure
+  }
+
+  pub fn fov(&self) -> Deg<f32> {
+    self.projection.fovy()
+  }
+
+  // The last frame drawn, without the UI, while a frame target is set. The texture belongs to the
+  // renderer and is replaced on resize, so look it up after each render rather than keeping it.
+  pub fn frame_texture(&self) -> Option<&Texture> {
+    self.frame_target.as_ref().map(FrameTarget::texture)
+  }
+
+  pub fn light_color(&self) -> [f32; 3] {
+    self.light_uniform.uniform.color
+  }
+
+  pub fn light_position(&self) -> [f32; 3] {
+    self.light_uniform.uniform.position
+  }
+
+  pub fn pipeline_reports(&self) -> &[PipelineReport] {
+    &self.pipeline_reports
+  }
+
+  pub fn culling_backfaces(&self) -> bool {
+    self.cull_backfaces
+  }
+
+  pub fn freezing_frustum(&self) -> bool {
+    self.frozen_frustum.is_some()
+  }
+
+  pub fn rendering_axes(&self) -> bool {
+    self.render_axes
+  }
+
+  pub fn rendering_bounds(&self) -> bool {
+    self.render_bounds
+  }
+
+  pub fn rendering_facing(&self) -> bool {
+    self.render_facing
+  }
+
+  pub fn rendering_instance_colors(&self) -> bool {
+    self.render_instance_colors
+  }
+
+  pub fn rendering_light(&self) -> bool {
+    self.render_light
+  }
+
+  pub fn rendering_minimap(&self) -> bool {
+    self.render_minimap
+  }
+
+  pub fn rendering_normals(&self) -> bool {
+    self.render_normals
+  }
+
+  pub fn rendering_points(&self) -> bool {
+    self.render_points
+  }
+
+  pub fn rendering_wireframe(&self) -> bool {
+    self.render_wireframe
+  }
+
+  pub fn rotating_light(&self) -> bool {
+    self.rotate_light
+  }
+
+  // Hand placement, returns false without moving while the light is animated or follows the camera
+  pub fn move_light(&mut self, offset: [f32; 3]) -> bool {
+    if self.rotate_light || self.headlight {
+      return false;
+    }
+
+    let [x, y, z] = self.light_uniform.uniform.position;
+
+    self.set_light_position([x + offset[0], y + offset[1], z + offset[2]]);
+    true
+  }
+
+  pub fn set_accumulate(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, accumulate: bool) {
+    self.needs_clear = true;
+    self.accumulation = if accumulate {
+      Some(self.accumulate_renderer.create_accumulation(device, config))
+    } else {
+      self.projection.set_jitter(Vector2::zero());
+      None
+    };
+  }
+
+  // Starts averaging again when the scene changes under a still camera
+  pub fn reset_accumulation(&mut self) {
+    if let Some(accumulation) = &mut self.accumulation {
+      accumulation.reset();
+    }
+  }
+
+  pub fn set_ambient(&mut self, [r, g, b]: [f32; 3]) {
+    self.shading_uniform.uniform.ambient = [r, g, b, 1.0];
+  }
+
+  // Fixes the width over height of the image, with black bars filling the rest of the target
+  pub fn set_aspect_lock(&mut self, aspect_lock: Option<f32>) {
+    let [width, height] = self.size;
+
+    self.projection.set_aspect_lock(aspect_lock, width, height);
+    self.update_viewport_uniforms();
+  }
+
+  pub fn set_background(&mut self, background: [f32; 3]) {
+    self.background = background;
+  }
+
+  pub fn set_point_mode(&mut self, render_points: bool) {
+    self.render_points = render_points;
+  }
+
+  pub fn set_point_size(&mut self, size: f32) {
+    self.point_uniform.uniform.size = size;
+  }
+
+  pub fn set_checker_background(&mut self, checker_background: bool) {
+    self.checker_background = checker_background;
+  }
+
+  // Keeps the side of n . p + d = 0 the normal points to, with the normal scaled to unit length
+  pub fn set_clip_plane(&mut self, plane: Option<[f32; 4]>) {
+    self.shading_uniform.uniform.clip_plane = match plane {
+      Some([x, y, z, d]) => {
+        let length = Vector3::new(x, y, z).magnitude();
+
+        if length > 0.0 {
+          [x / length, y / length, z / length, d / length]
+        } else {
+          [0.0; 4]
+        }
+      }
+      None => [0.0; 4],
+    };
+  }
+
+  // Slides an active clip plane along its normal
+  pub fn move_clip_plane(&mut self, distance: f32) {
+    let plane = &mut self.shading_uniform.uniform.clip_plane;
+
+    if plane[..3].iter().any(|&n| n != 0.0) {
+      plane[3] -= distance;
+    }
+  }
+
+  pub fn set_clear(&mut self, clear_color: bool, clear_depth: bool) {
+    self.clear_color = clear_color;
+    self.clear_depth = clear_depth;
+  }
+
+  pub fn set_cull_backfaces(&mut self, cull_backfaces: bool) {
+    self.cull_backfaces = cull_backfaces;
+  }
+
+  pub fn adjust_exposure(&mut self, delta: f32) {
+    self.set_exposure(self.exposure() + delta);
+  }
+
+  pub fn set_exposure(&mut self, exposure: f32) {
+    self.shading_uniform.uniform.exposure = exposure.clamp(-EXPOSURE_LIMIT, EXPOSURE_LIMIT);
+  }
+
+  pub fn set_fake_ao(&mut self, strength: f32) {
+    self.shading_uniform.uniform.ao_strength = strength;
+  }
+
+  pub fn set_fog(&mut self, color: Option<[f32; 3]>, density: f32, start: f32, end: f32) {
+    let shading = &mut self.shading_uniform.uniform;
+    let [r, g, b] = color.unwrap_or(self.background);
+
+    shading.fog_color = [r, g, b, 1.0];
+    shading.fog_density = density;
+    shading.fog_start = start;
+    shading.fog_end = end;
+  }
+
+  pub fn set_fov(&mut self, fovy: Deg<f32>) {
+    self.projection.set_fovy(fovy);
+  }
+
+  // Draws into an offscreen copy of the surface that frame_texture hands out, then blits it to the window
+  pub fn set_frame_target(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, enabled: bool) {
+    self.frame_target = enabled.then(|| self.blit_renderer.create_frame_target(device, config));
+  }
+
+  pub fn set_gpu_debug(&mut self, gpu_debug: bool) {
+    self.gpu_debug = gpu_debug;
+  }
+
+  pub fn set_grid(&mut self, device: &wgpu::Device, grid: Option<Grid>) {
+    self.grid = grid;
+    self.grid_mesh = (grid == Some(Grid::Lines)).then(|| grid_mesh(device));
+  }
+
+  // Keeps the light at the camera, overriding the orbit animation
+  pub fn set_headlight(&mut self, headlight: bool) {
+    self.headlight = headlight;
+  }
+
+  pub fn set_light_color(&mut self, color: [f32; 3]) {
+    self.light_uniform.uniform.color = color;
+  }
+
+  // Moves the light onto a circle around the vertical through the orbit center, keeping its
+  // current angle around it. The height is above the center.
+  pub fn set_light_orbit(&mut self, radius: Option<f32>, height: Option<f32>, speed: Deg<f32>) {
+    let center = self.light_orbit_center;
+    let [x, y, z] = self.light_uniform.uniform.position;
+    let (x, y, z) = (x - center.x, y - center.y, z - center.z);
+    let angle = z.atan2(x);
+    let radius = radius.unwrap_or_else(|| x.hypot(z));
+    let height = height.unwrap_or(y);
+
+    self.light_orbit_speed = speed;
+    self.set_light_position((center + Vector3::new(radius * angle.cos(), height, radius * angle.sin())).into());
+  }
+
+  // Carries the light along with the center, so it keeps its place relative to it
+  pub fn set_light_orbit_center(&mut self, center: Vector3<f32>) {
+    let position = Vector3::from(self.light_uniform.uniform.position) + center - self.light_orbit_center;
+
+    self.light_orbit_center = center;
+    self.set_light_position(position.into());
+  }
+
+  pub fn set_light_position(&mut self, position: [f32; 3]) {
+    self.light_uniform.uniform.position = position;
+    if let Some(accumulation) = &mut self.accumulation {
+      accumulation.reset();
+    }
+  }
+
+  pub fn set_light_render(&mut self, render_light: bool) {
+    self.render_light = render_light;
+  }
+
+  pub fn set_light_rotation(&mut self, rotate_light: bool) {
+    self.rotate_light = rotate_light;
+  }
+
+  pub fn set_material(&mut self, metallic: f32, roughness: f32) {
+    self.shading_uniform.uniform.metallic = metallic;
+    self.shading_uniform.uniform.roughness = roughness;
+  }
+
+  // Outlines this instance, counting through every model's instances in order
+  pub fn set_selected_instance(&mut self, selected_instance: Option<usize>) {
+    self.selected_instance = selected_instance;
+  }
+
+  pub fn set_shading_model(&mut self, shading_model: ShadingModel) {
+    self.shading_model = shading_model;
+  }
+
+  pub fn set_specular(&mut self, shininess: f32, strength: f32) {
+    self.shading_uniform.uniform.shininess = shininess;
+    self.shading_uniform.uniform.specular_strength = strength;
+  }
+
+  // Shading model for the right half of the target, or None to draw the whole target with one
+  pub fn set_split(&mut self, split_shading_model: Option<ShadingModel>) {
+    self.split_shading_model = split_shading_model;
+  }
+
+  // Clears to fully transparent, premultiplied, so a transparent window shows what is behind it
+  pub fn set_transparent(&mut self, transparent: bool) {
+    self.transparent = transparent;
+  }
+
+  pub fn set_wireframe(&mut self, render_wireframe: bool) {
+    self.render_wireframe = render_wireframe;
+  }
+
+  pub fn set_wire_width(&mut self, width: Option<f32>) {
+    self.wire_width = width;
+    if let Some(width) = width {
+      self.wire_uniform.uniform.width = width;
+    }
+  }
+
+  // 0 is plain Lambert, 1 lights the whole sphere half-Lambert style
+  pub fn set_wrap(&mut self, wrap: f32) {
+    self.shading_uniform.uniform.wrap = wrap.clamp(0.0, 1.0);
+  }
+
+  pub fn set_xray(&mut self, render_xray: bool) {
+    self.render_xray = render_xray;
+  }
+
+  pub fn toggle_axes(&mut self) {
+    self.render_axes = !self.render_axes;
+  }
+
+  pub fn toggle_bounds(&mut self) {
+    self.render_bounds = !self.render_bounds;
+  }
+
+  pub fn toggle_cull_backfaces(&mut self) {
+    self.cull_backfaces = !self.cull_backfaces;
+  }
+
+  pub fn toggle_facing(&mut self) {
+    self.render_facing = !self.render_facing;
+  }
+
+  pub fn toggle_normals(&mut self) {
+    self.render_normals = !self.render_normals;
+  }
+
+  // Freezes the current view volume as 12 world space edges, or releases a frozen one
+  pub fn toggle_frustum<C: Camera>(&mut self, device: &wgpu::Device, camera: &C) {
+    if self.frozen_frustum.take().is_some() {
+      return;
     }
+
+    let inverse = match (self.projection.calc_unjittered_matrix() * camera.projection()).invert() {
+      Some(inverse) => inverse,
+      None => return,
+    };
+    let mut builder = MeshBuilder::new("Frustum");
+
+    // Corners of the clip volume, with bit 1 picking x, bit 2 y and bit 4 near or far
+    for i in 0..8u32 {
+      let x = if i & 1 == 0 { -1.0 } else { 1.0 };
+      let y = if i & 2 == 0 { -1.0 } else { 1.0 };
+      let z = if i & 4 == 0 { 0.0 } else { 1.0 };
+      let corner = inverse * Vector4::new(x, y, z, 1.0);
+
+      builder.add_colored_vertex(corner.truncate() / corner.w, Vector3::unit_y(), FRUSTUM_COLOR);
+    }
+    for i in 0..8u32 {
+      for bit in [1, 2, 4] {
+        if i & bit == 0 {
+          builder.add_edge((i, i | bit));
+        }
+      }
+    }
+    builder.set_topology(wgpu::PrimitiveTopology::LineList);
+    self.frozen_frustum = Some(builder.build(device));
+  }
+
+  pub fn toggle_instance_colors(&mut self) {
+    self.render_instance_colors = !self.render_instance_colors;
   }
 
   pub fn toggle_light_render(&mut self) {
@@ -95,9 +853,25 @@ impl Renderer {
     self.rotate_light = !self.rotate_light;
   }
 
+  pub fn toggle_minimap(&mut self) {
+    self.render_minimap = !self.render_minimap;
+  }
+
   pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
-    self.depth_texture = Texture::create_depth_texture(device, config, "depth_texture");
+    self.depth_texture = self.depth_format.map(|format| Texture::create_depth_texture(device, config, format, "depth_texture"));
+    self.needs_clear = true;
+    if self.accumulation.is_some() {
+      self.accumulation = Some(self.accumulate_renderer.create_accumulation(device, config));
+    }
+    if self.frame_target.is_some() {
+      self.frame_target = Some(self.blit_renderer.create_frame_target(device, config));
+    }
+    if self.gbuffer.is_some() {
+      self.gbuffer = Some(self.deferred_renderer.create_gbuffer(device, config));
+    }
     self.projection.resize(config.width, config.height);
+    self.size = [config.width, config.height];
+    self.update_viewport_uniforms();
   }
 
   pub fn render(
@@ -106,37 +880,101 @@ impl Renderer {
     queue: &wgpu::Queue,
     view: &wgpu::TextureView,
     light_model: &Model,
-    models: &Vec<Model>,
-    instance_buffer: &wgpu::Buffer,
+    models: &[Model],
+    ui: Option<&mut Ui>,
   ) {
+      // Skipped clears still happen on the first frame after (re)creating the targets,
+      // since their contents are undefined until then
+      let clear_color = self.clear_color || self.needs_clear;
+      let color_load = if clear_color && !self.checker_background {
+        let [r, g, b] = self.background;
+        let alpha = if self.transparent { 0.0 } else { 1.0 };
+
+        wgpu::LoadOp::Clear(ColorUniform { color: [r * alpha, g * alpha, b * alpha, alpha] }.into())
+      } else {
+        wgpu::LoadOp::Load
+      };
+      let depth_load = if self.clear_depth || self.needs_clear {
+        wgpu::LoadOp::Clear(1.0)
+      } else {
+        wgpu::LoadOp::Load
+      };
       let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
         label: Some("Render Encoder"),
       });
+
+      let output = self.frame_target.as_ref().map_or(view, FrameTarget::view);
+      let target = self.accumulation.as_ref().map_or(output, Accumulation::frame_view);
+
+      self.needs_clear = false;
+      if clear_color && self.checker_background {
+        self.push_debug_group(&mut encoder, "Background");
+        self.checker_renderer.render(&mut encoder, target);
+        self.pop_debug_group(&mut encoder);
+      }
+
+      // The debug views stay on the forward path
+      let deferred = self.gbuffer.as_ref()
+        .filter(|_| !self.render_facing && !self.render_instance_colors && !self.render_points);
+      let (color_load, depth_load) = match deferred {
+        Some(gbuffer) => {
+          self.push_debug_group(&mut encoder, "Deferred");
+          self.deferred_renderer.render(
+            &mut encoder,
+            gbuffer,
+            target,
+            color_load,
+            self.depth_texture.as_ref().map(|texture| &texture.view),
+            depth_load,
+            self.viewport(),
+            models,
+            &self.shading_uniform.bind_group,
+            &self.camera_uniform.bind_group,
+            &self.light_uniform.bind_group,
+          );
+          self.pop_debug_group(&mut encoder);
+          (wgpu::LoadOp::Load, wgpu::LoadOp::Load)
+        }
+        None => (color_load, depth_load),
+      };
+      self.push_debug_group(&mut encoder, "Main");
       {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
           label: Some("Render Pass"),
           color_attachments: &[
             wgpu::RenderPassColorAttachment {
-              view,
+              view: target,
               resolve_target: None,
               ops: wgpu::Operations {
-                load: wgpu::LoadOp::Clear(self.ambient_uniform.uniform.into()),
+                load: color_load,
                 store: true,
               },
             }
           ],
-          depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-            view: &self.depth_texture.view,
+          depth_stencil_attachment: self.depth_texture.as_ref().map(|texture| wgpu::RenderPassDepthStencilAttachment {
+            view: &texture.view,
             depth_ops: Some(wgpu::Operations {
-              load: wgpu::LoadOp::Clear(1.0),
+              load: depth_load,
               store: true,
             }),
             stencil_ops: None,
           }),
       });
-      render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+
+      let [viewport_x, viewport_y, viewport_width, viewport_height] = self.viewport();
+
+      render_pass.set_viewport(
+        viewport_x as f32,
+        viewport_y as f32,
+        viewport_width as f32,
+        viewport_height as f32,
+        0.0,
+        1.0,
+      );
+      render_pass.set_scissor_rect(viewport_x, viewport_y, viewport_width, viewport_height);
 
       if self.render_light {
+        self.debug_marker(&mut render_pass, "Light");
         self.light_renderer.render(
           &mut render_pass,
           light_model,
@@ -145,34 +983,577 @@ impl Renderer {
         );
       }
 
+      // Split mode draws each half of the viewport with its own shading model
+      let halves = match self.split_shading_model {
+        Some(right) => vec![
+          (self.shading_model, viewport_x, viewport_width / 2),
+          (right, viewport_x + viewport_width / 2, viewport_width - viewport_width / 2),
+        ],
+        None => vec![(self.shading_model, viewport_x, viewport_width)],
+      };
+
+      if !self.render_points && deferred.is_none() {
+        self.debug_marker(&mut render_pass, "Models");
+        for (shading_model, x, half_width) in halves {
+          let model_renderer = if self.render_facing {
+            &self.facing_renderer
+          } else if self.render_instance_colors {
+            &self.instance_renderer
+          } else {
+            self.shaded_renderer(shading_model)
+          };
+
+          render_pass.set_scissor_rect(x, viewport_y, half_width, viewport_height);
+          for model in models {
+            model_renderer.render(
+              &mut render_pass,
+              model,
+              &self.shading_uniform.bind_group,
+              &self.camera_uniform.bind_group,
+              &self.light_uniform.bind_group,
+            );
+          }
+        }
+        render_pass.set_scissor_rect(viewport_x, viewport_y, viewport_width, viewport_height);
+      }
+
+      if let Some((model, index)) = self.selected_instance.and_then(|index| instance_at(models, index)) {
+        if !self.render_points {
+          self.debug_marker(&mut render_pass, "Outline");
+          self.outline_renderer.render_instances(
+            &mut render_pass,
+            model,
+            index..index + 1,
+            &self.shading_uniform.bind_group,
+            &self.camera_uniform.bind_group,
+            &self.light_uniform.bind_group,
+          );
+        }
+      }
+
+      self.debug_marker(&mut render_pass, "Points");
       for model in models {
-        self.model_renderer.render(
+        let render_points = self.render_points;
+        let point_meshes = model.meshes.iter()
+          .filter(|mesh| mesh.visible)
+          .filter(|mesh| render_points || mesh.topology == wgpu::PrimitiveTopology::PointList);
+
+        self.point_renderer.render(
           &mut render_pass,
-          model,
-          &self.ambient_uniform.bind_group,
+          point_meshes,
+          model.instances.buffer(),
+          model.instances.range(),
           &self.camera_uniform.bind_group,
-          &self.light_uniform.bind_group,
+          &self.point_uniform.bind_group,
         );
       }
+
+      // Polygon outlines drawn over the shaded faces, for models that were loaded with them
+      if self.render_wireframe {
+        self.debug_marker(&mut render_pass, "Wireframe");
+        for model in models {
+          let edges = model.meshes.iter().filter(|mesh| mesh.visible).filter_map(|mesh| mesh.edges.as_deref());
+
+          if self.wire_width.is_some() {
+            self.wire_renderer.render(
+              &mut render_pass,
+              edges,
+              model.instances.buffer(),
+              model.instances.range(),
+              &self.camera_uniform.bind_group,
+              &self.wire_uniform.bind_group,
+            );
+          } else {
+            self.line_renderer.render(
+              &mut render_pass,
+              edges,
+              model.instances.buffer(),
+              model.instances.range(),
+              &self.camera_uniform.bind_group,
+            );
+          }
+        }
+      }
+
+      if self.render_bounds {
+        self.debug_marker(&mut render_pass, "Bounds");
+        for model in models {
+          self.line_renderer.render(
+            &mut render_pass,
+            model.bounds_mesh.iter(),
+            model.instances.buffer(),
+            model.instances.range(),
+            &self.camera_uniform.bind_group,
+          );
+        }
+      }
+
+      if self.render_normals {
+        self.debug_marker(&mut render_pass, "Normals");
+        for model in models {
+          self.line_renderer.render(
+            &mut render_pass,
+            model.meshes.iter().filter(|mesh| mesh.visible).filter_map(|mesh| mesh.normals.as_deref()),
+            model.instances.buffer(),
+            model.instances.range(),
+            &self.camera_uniform.bind_group,
+          );
+        }
+      }
+
+      // After the opaque geometry, so the shader grid blends over the background only where it shows
+      self.debug_marker(&mut render_pass, "Grid");
+      match self.grid {
+        Some(Grid::Lines) => self.line_renderer.render(
+          &mut render_pass,
+          self.grid_mesh.iter(),
+          self.world_instances.buffer(),
+          self.world_instances.range(),
+          &self.camera_uniform.bind_group,
+        ),
+        Some(Grid::Shader) => self.grid_renderer.render(
+          &mut render_pass,
+          &self.camera_uniform.bind_group,
+          &self.grid_uniform.bind_group,
+        ),
+        None => {}
+      }
+
+      self.line_renderer.render(
+        &mut render_pass,
+        self.frozen_frustum.iter(),
+        self.world_instances.buffer(),
+        self.world_instances.range(),
+        &self.camera_uniform.bind_group,
+      );
+
+      if self.render_xray && !self.render_points {
+        self.debug_marker(&mut render_pass, "X-ray");
+        for model in models {
+          self.xray_renderer.render(
+            &mut render_pass,
+            model,
+            &self.shading_uniform.bind_group,
+            &self.camera_uniform.bind_group,
+            &self.light_uniform.bind_group,
+          );
+        }
+      }
+    }
+    self.pop_debug_group(&mut encoder);
+    self.push_debug_group(&mut encoder, "Post");
+    if let Some(accumulation) = &mut self.accumulation {
+      self.accumulate_renderer.render(&mut encoder, accumulation, output);
+    }
+    if self.projection.aspect_lock().is_some() {
+      self.letterbox_renderer.render(&mut encoder, output, self.size, self.viewport());
+    }
+    if self.render_minimap {
+      self.draw_minimap(queue, &mut encoder, output, models);
+    }
+    if self.render_axes {
+      self.draw_axes(&mut encoder, output);
+    }
+    if let Some(frame_target) = &self.frame_target {
+      self.blit_renderer.render(&mut encoder, frame_target, view);
+    }
+    if let Some(ui) = ui {
+      ui.paint(device, queue, &mut encoder, view);
     }
+    self.pop_debug_group(&mut encoder);
     queue.submit(std::iter::once(encoder.finish()));
   }
 
+  fn shaded_renderer(&self, shading_model: ShadingModel) -> &ModelRenderer {
+    match (shading_model, self.cull_backfaces) {
+      (ShadingModel::Phong, true) => &self.model_renderer,
+      (ShadingModel::Phong, false) => &self.double_sided_renderer,
+      (ShadingModel::Pbr, true) => &self.pbr_renderer,
+      (ShadingModel::Pbr, false) => &self.double_sided_pbr_renderer,
+    }
+  }
+
+  fn push_debug_group(&self, encoder: &mut wgpu::CommandEncoder, label: &str) {
+    if self.gpu_debug {
+      encoder.push_debug_group(label);
+    }
+  }
+
+  fn pop_debug_group(&self, encoder: &mut wgpu::CommandEncoder) {
+    if self.gpu_debug {
+      encoder.pop_debug_group();
+    }
+  }
+
+  fn debug_marker(&self, render_pass: &mut wgpu::RenderPass, label: &str) {
+    if self.gpu_debug {
+      render_pass.insert_debug_marker(label);
+    }
+  }
+
+  // Orientation inset in the bottom left corner of the viewport. It clears the depth buffer to draw
+  // over the scene.
+  fn draw_axes(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+    let [viewport_x, viewport_y, width, height] = self.viewport();
+    let size = width.min(height) / 6;
+
+    if size == 0 {
+      return;
+    }
+
+    let x = viewport_x + MINIMAP_MARGIN.min(width - size);
+    let y = viewport_y + height.saturating_sub(size + MINIMAP_MARGIN);
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+      label: Some("Axes Pass"),
+      color_attachments: &[
+        wgpu::RenderPassColorAttachment {
+          view,
+          resolve_target: None,
+          ops: wgpu::Operations {
+            load: wgpu::LoadOp::Load,
+            store: true,
+          },
+        }
+      ],
+      depth_stencil_attachment: self.depth_texture.as_ref().map(|texture| wgpu::RenderPassDepthStencilAttachment {
+        view: &texture.view,
+        depth_ops: Some(wgpu::Operations {
+          load: wgpu::LoadOp::Clear(1.0),
+          store: true,
+        }),
+        stencil_ops: None,
+      }),
+    });
+
+    render_pass.set_viewport(x as f32, y as f32, size as f32, size as f32, 0.0, 1.0);
+    render_pass.set_scissor_rect(x, y, size, size);
+    self.line_renderer.render(
+      &mut render_pass,
+      std::iter::once(&self.axes_mesh),
+      self.world_instances.buffer(),
+      self.world_instances.range(),
+      &self.axes_camera.bind_group,
+    );
+  }
+
+  // Top-down orthographic inset in the bottom right corner, framing every model with the main
+  // camera's footprint on the ground drawn over them. It clears the depth buffer to do so.
+  fn draw_minimap(
+    &self,
+    queue: &wgpu::Queue,
+    encoder: &mut wgpu::CommandEncoder,
+    view: &wgpu::TextureView,
+    models: &[Model],
+  ) {
+    let [width, height] = self.size;
+    let size = width.min(height) / 4;
+    let bounds = models.iter()
+      .fold(Aabb::empty(), |bounds, model| bounds.union(model.world_bounds()));
+    let bounds = match self.minimap_footprint.first() {
+      Some(eye) => bounds.grow(eye.to_vec()),
+      None => bounds,
+    };
+
+    if size == 0 || bounds.is_empty() {
+      return;
+    }
+
+    let region = [
+      width.saturating_sub(size + MINIMAP_MARGIN),
+      height.saturating_sub(size + MINIMAP_MARGIN),
+      size,
+      size,
+    ];
+    let center = bounds.center();
+    let extent = bounds.size();
+    let half = (extent.x.max(extent.z) * 0.55).max(0.5);
+    let eye = Point3::new(center.x, bounds.max.y + 1.0, center.z);
+    // Looking straight down with -z at the top of the inset
+    let view_matrix = Matrix4::look_to_rh(eye, -Vector3::unit_y(), -Vector3::unit_z());
+    let projection = OPENGL_TO_WGPU_MATRIX * ortho(-half, half, -half, half, 0.0, extent.y + 2.0);
+    let mut camera_uniform = CameraUniform::new();
+
+    camera_uniform.set_view_proj(eye, projection * view_matrix);
+    queue.write_buffer(&self.minimap_camera.buffer, 0, bytemuck::cast_slice(&[camera_uniform]));
+
+    // Lifted above the scene so the footprint isn't hidden by it
+    let footprint = (self.minimap_footprint.len() == 5).then(|| {
+      let vertices = self.minimap_footprint.iter().map(|point| MeshVertex {
+        position: [point.x, bounds.max.y + 0.5, point.z],
+        normal: [0.0, 1.0, 0.0],
+        color: FOOTPRINT_COLOR,
+      }).collect::<Vec<_>>();
+
+      queue.write_buffer(&self.minimap_footprint_mesh.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+      &self.minimap_footprint_mesh
+    });
+    let model_renderer = self.shaded_renderer(self.shading_model);
+    let [x, y, region_width, region_height] = region;
+
+    self.checker_renderer.render_region(encoder, view, region);
+
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+      label: Some("Minimap Pass"),
+      color_attachments: &[
+        wgpu::RenderPassColorAttachment {
+          view,
+          resolve_target: None,
+          ops: wgpu::Operations {
+            load: wgpu::LoadOp::Load,
+            store: true,
+          },
+        }
+      ],
+      depth_stencil_attachment: self.depth_texture.as_ref().map(|texture| wgpu::RenderPassDepthStencilAttachment {
+        view: &texture.view,
+        depth_ops: Some(wgpu::Operations {
+          load: wgpu::LoadOp::Clear(1.0),
+          store: true,
+        }),
+        stencil_ops: None,
+      }),
+    });
+
+    render_pass.set_viewport(x as f32, y as f32, region_width as f32, region_height as f32, 0.0, 1.0);
+    render_pass.set_scissor_rect(x, y, region_width, region_height);
+    for model in models {
+      model_renderer.render(
+        &mut render_pass,
+        model,
+        &self.shading_uniform.bind_group,
+        &self.minimap_camera.bind_group,
+        &self.light_uniform.bind_group,
+      );
+    }
+    self.line_renderer.render(
+      &mut render_pass,
+      footprint.into_iter(),
+      self.world_instances.buffer(),
+      self.world_instances.range(),
+      &self.minimap_camera.bind_group,
+    );
+  }
+
   pub fn update(&mut self, queue: &wgpu::Queue, dt: std::time::Duration) {
     queue.write_buffer(&self.camera_uniform.buffer, 0, bytemuck::cast_slice(&[self.camera_uniform.uniform]));
 
-    if self.rotate_light {
-      let old_position: Vector3<_> = self.light_uniform.uniform.position.into();
+    if self.rotate_light && !self.headlight && self.light_orbit_speed != Deg(0.0) {
+      let old_position: Vector3<_> = Vector3::from(self.light_uniform.uniform.position) - self.light_orbit_center;
 
       self.light_uniform.uniform.position = (
-          Quaternion::from_axis_angle((0.0, 1.0, 0.0).into(), Deg(60.0 * dt.as_secs_f32()))* old_position
+          Quaternion::from_axis_angle((0.0, 1.0, 0.0).into(), self.light_orbit_speed * dt.as_secs_f32())* old_position
+          + self.light_orbit_center
       ).into();
+      if let Some(accumulation) = &mut self.accumulation {
+        accumulation.reset();
+      }
     }
 
     queue.write_buffer(&self.light_uniform.buffer, 0, bytemuck::cast_slice(&[self.light_uniform.uniform]));
+    queue.write_buffer(&self.point_uniform.buffer, 0, bytemuck::cast_slice(&[self.point_uniform.uniform]));
+    queue.write_buffer(&self.shading_uniform.buffer, 0, bytemuck::cast_slice(&[self.shading_uniform.uniform]));
+    queue.write_buffer(&self.wire_uniform.buffer, 0, bytemuck::cast_slice(&[self.wire_uniform.uniform]));
+    queue.write_buffer(&self.grid_uniform.buffer, 0, bytemuck::cast_slice(&[self.grid_uniform.uniform]));
+    queue.write_buffer(&self.axes_camera.buffer, 0, bytemuck::cast_slice(&[self.axes_camera.uniform]));
+  }
+
+  // World space points on the near and far planes under a position in normalized device coordinates
+  pub fn unproject<C: Camera>(&self, camera: &C, ndc: Vector2<f32>) -> Option<(Point3<f32>, Point3<f32>)> {
+    let inverse = (self.projection.calc_unjittered_matrix() * camera.projection()).invert()?;
+    let unproject = |depth| {
+      let point = inverse * Vector4::new(ndc.x, ndc.y, depth, 1.0);
+
+      Point3::new(point.x / point.w, point.y / point.w, point.z / point.w)
+    };
+
+    Some((unproject(0.0), unproject(1.0)))
+  }
+
+  // The camera position followed by where the corners of the view meet the ground, or the far plane
+  // for corners that never reach it
+  fn ground_footprint<C: Camera>(&self, camera: &C) -> Vec<Point3<f32>> {
+    let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+
+    std::iter::once(camera.get_position())
+      .chain(corners.iter().filter_map(|&(x, y)| {
+        let (near, far) = self.unproject(camera, Vector2::new(x, y))?;
+        let direction = far - near;
+        let t = if direction.y < 0.0 { (-near.y / direction.y).min(1.0) } else { 1.0 };
+
+        Some(near + direction * t)
+      }))
+      .collect()
+  }
+
+  pub fn zoom_fov(&mut self, delta: Deg<f32>) {
+    self.projection.zoom(delta);
+  }
+
+  // Pixel sized points and wires are measured against the viewport they are drawn into
+  fn update_viewport_uniforms(&mut self) {
+    let [_, _, width, height] = self.viewport();
+
+    self.point_uniform.uniform.viewport = [width as f32, height as f32];
+    self.wire_uniform.uniform.viewport = [width as f32, height as f32];
+  }
+
+  // The [x, y, width, height] part of the target the scene is drawn into, centered between bars
+  // when the aspect is locked
+  pub fn viewport(&self) -> [u32; 4] {
+    let [width, height] = self.size;
+
+    match self.projection.aspect_lock() {
+      Some(aspect) if width as f32 > aspect * height as f32 => {
+        let viewport_width = ((aspect * height as f32).round() as u32).min(width);
+
+        [(width - viewport_width) / 2, 0, viewport_width, height]
+      }
+      Some(aspect) => {
+        let viewport_height = ((width as f32 / aspect).round() as u32).min(height);
+
+        [0, (height - viewport_height) / 2, width, viewport_height]
+      }
+      None => [0, 0, width, height],
+    }
   }
 
   pub fn update_camera_uniform<C: Camera>(&mut self, camera: &C) {
+    if let Some(accumulation) = &mut self.accumulation {
+      // The camera is dirty whenever its unjittered view projection moves
+      let view_proj = self.projection.calc_unjittered_matrix() * camera.projection();
+
+      if view_proj != self.last_view_proj {
+        accumulation.reset();
+        self.last_view_proj = view_proj;
+      }
+      self.projection.set_jitter(accumulation.jitter());
+    }
+    if self.headlight {
+      self.light_uniform.uniform.position = camera.get_position().into();
+    }
+    if self.render_minimap {
+      self.minimap_footprint = self.ground_footprint(camera);
+    }
     self.camera_uniform.uniform.update_view_proj(camera, &self.projection);
+    if self.render_axes {
+      // The camera's turn without its position, looking at the axes from a fixed distance
+      let mut rotation = camera.projection();
+
+      rotation.w = Vector4::unit_w();
+
+      let view_matrix = Matrix4::from_translation(-AXES_DISTANCE * Vector3::unit_z()) * rotation;
+      let projection = OPENGL_TO_WGPU_MATRIX * ortho(-1.2, 1.2, -1.2, 1.2, 0.0, 2.0 * AXES_DISTANCE);
+
+      self.axes_camera.uniform.set_view_proj(Point3::new(0.0, 0.0, AXES_DISTANCE), projection * view_matrix);
+    }
+    if self.grid == Some(Grid::Shader) {
+      let view_proj = self.projection.calc_matrix() * camera.projection();
+
+      if let Some(inverse) = view_proj.invert() {
+        self.grid_uniform.uniform.inverse_view_proj = inverse.into();
+      }
+    }
   }
 }
+
+// Unit x, y and z arrows in red, green and blue, each with a two stroke head
+fn axes_mesh(device: &wgpu::Device) -> Mesh {
+  let mut builder = MeshBuilder::new("Axes");
+  let axes = [
+    (Vector3::unit_x(), Vector3::unit_y(), [1.0, 0.2, 0.2, 1.0]),
+    (Vector3::unit_y(), Vector3::unit_z(), [0.2, 1.0, 0.2, 1.0]),
+    (Vector3::unit_z(), Vector3::unit_x(), [0.3, 0.5, 1.0, 1.0]),
+  ];
+
+  for (axis, across, color) in axes {
+    let first = builder.vertex_count();
+    let head = axis * 0.8;
+
+    for point in [Vector3::zero(), axis, head + across * 0.1, head - across * 0.1] {
+      builder.add_colored_vertex(point, axis, color);
+    }
+    builder.add_edge((first, first + 1));
+    builder.add_edge((first + 1, first + 2));
+    builder.add_edge((first + 1, first + 3));
+  }
+  builder.set_topology(wgpu::PrimitiveTopology::LineList);
+
+  builder.build(device)
+}
+
+// The eye and the four ground corners from Renderer::ground_footprint, with a line from the eye
+// to each corner and around the corners. The positions are written in when the minimap is drawn.
+fn footprint_mesh(device: &wgpu::Device) -> Mesh {
+  let mut builder = MeshBuilder::new("Footprint");
+
+  for _ in 0..5 {
+    builder.add_colored_vertex(Vector3::zero(), Vector3::unit_y(), FOOTPRINT_COLOR);
+  }
+  for i in 1..5 {
+    builder.add_edge((0, i));
+    builder.add_edge((i, i % 4 + 1));
+  }
+  builder.set_topology(wgpu::PrimitiveTopology::LineList);
+
+  builder.build_writable(device)
+}
+
+// The model holding the index-th instance across all models, and the instance's index within it
+fn instance_at(models: &[Model], index: usize) -> Option<(&Model, u32)> {
+  let mut first = 0;
+
+  for model in models {
+    let count = model.instances.instances.len();
+
+    if index < first + count {
+      return Some((model, (index - first) as u32));
+    }
+    first += count;
+  }
+
+  None
+}
+
+// Unit spaced lines on the ground, with the axes through the origin included
+fn grid_mesh(device: &wgpu::Device) -> Mesh {
+  let mut builder = MeshBuilder::new("Grid");
+  let extent = GRID_EXTENT as f32;
+
+  for i in -GRID_EXTENT..=GRID_EXTENT {
+    let offset = i as f32;
+    let first = builder.vertex_count();
+
+    builder.add_colored_vertex([offset, 0.0, -extent], Vector3::unit_y(), GRID_COLOR);
+    builder.add_colored_vertex([offset, 0.0, extent], Vector3::unit_y(), GRID_COLOR);
+    builder.add_colored_vertex([-extent, 0.0, offset], Vector3::unit_y(), GRID_COLOR);
+    builder.add_colored_vertex([extent, 0.0, offset], Vector3::unit_y(), GRID_COLOR);
+    builder.add_edge((first, first + 1));
+    builder.add_edge((first + 2, first + 3));
+  }
+  builder.set_topology(wgpu::PrimitiveTopology::LineList);
+
+  builder.build(device)
+}
+
+// Builds a pipeline inside an error scope so a broken shader or layout is reported by name
+fn build_pipeline<T, F>(
+  device: &wgpu::Device,
+  reports: &mut Vec<PipelineReport>,
+  label: &'static str,
+  build: F,
+) -> T
+where
+  F: FnOnce() -> T,
+{
+  device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+  let pipeline = build();
+  let error = pollster::block_on(device.pop_error_scope());
+
+  if let Some(error) = &error {
+    log::error!("{} pipeline failed to build: {}", label, error);
+  }
+  reports.push(PipelineReport { label, error });
+
+  pipeline
+}
@@ -0,0 +1,7 @@
+[[group(0), binding(0)]]
+var source: texture_2d<f32>;
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    return textureLoad(source, vec2<i32>(position.xy), 0);
+}
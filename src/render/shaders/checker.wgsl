@@ -0,0 +1,14 @@
+let CHECKER_SIZE: f32 = 16.0;
+let CHECKER_DARK: vec4<f32> = vec4<f32>(0.4, 0.4, 0.4, 1.0);
+let CHECKER_LIGHT: vec4<f32> = vec4<f32>(0.6, 0.6, 0.6, 1.0);
+
+// Squares are sized in pixels so they stay the same at any window size
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let cell = vec2<i32>(floor(position.xy / CHECKER_SIZE));
+
+    if (((cell.x + cell.y) & 1) == 0) {
+        return CHECKER_LIGHT;
+    }
+    return CHECKER_DARK;
+}
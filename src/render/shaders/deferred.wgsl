@@ -0,0 +1,51 @@
+
+// Deferred shading, appended to shader.wgsl so it shares its uniforms and helpers
+
+struct GBufferOutput {
+    [[location(0)]] position: vec4<f32>;
+    [[location(1)]] normal: vec4<f32>;
+    [[location(2)]] albedo: vec4<f32>;
+    [[location(3)]] emissive: vec4<f32>;
+};
+
+[[stage(fragment)]]
+fn fs_gbuffer(in: VertexOutput) -> GBufferOutput {
+    if (clipped(in.position)) {
+        discard;
+    }
+
+    var out: GBufferOutput;
+    out.position = vec4<f32>(in.position, 1.0);
+    out.normal = vec4<f32>(normalize(in.normal), 0.0);
+    out.albedo = vec4<f32>(base_color(in.color) * fake_ao(in.normal), in.color.a);
+    out.emissive = vec4<f32>(mesh.emissive, 0.0);
+
+    return out;
+}
+
+[[group(3), binding(1)]]
+var gbuffer_position: texture_2d<f32>;
+[[group(3), binding(2)]]
+var gbuffer_normal: texture_2d<f32>;
+[[group(3), binding(3)]]
+var gbuffer_albedo: texture_2d<f32>;
+[[group(3), binding(4)]]
+var gbuffer_emissive: texture_2d<f32>;
+
+// fs_main's lighting, with the surface read back from the G-buffer
+[[stage(fragment)]]
+fn fs_lighting([[builtin(position)]] coord: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let pixel = vec2<i32>(coord.xy);
+    let position = textureLoad(gbuffer_position, pixel, 0);
+
+    if (position.w == 0.0) {
+        discard;
+    }
+
+    let normal = textureLoad(gbuffer_normal, pixel, 0).xyz;
+    let albedo = textureLoad(gbuffer_albedo, pixel, 0);
+    let emissive = textureLoad(gbuffer_emissive, pixel, 0).xyz;
+    let color = phong(position.xyz, normal, albedo.xyz, emissive);
+
+    return vec4<f32>(apply_fog(apply_exposure(color), position.xyz), albedo.w);
+}
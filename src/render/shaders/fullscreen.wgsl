@@ -0,0 +1,14 @@
+// Fullscreen triangle covering the viewport, prepended to the shaders that draw one
+
+// Corner of the triangle in normalized device coordinates
+fn fullscreen_ndc(index: u32) -> vec2<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    return uv * 2.0 - 1.0;
+}
+
+[[stage(vertex)]]
+fn vs_fullscreen([[builtin(vertex_index)]] index: u32) -> [[builtin(position)]] vec4<f32> {
+    return vec4<f32>(fullscreen_ndc(index), 0.0, 1.0);
+}
+
@@ -0,0 +1,81 @@
+struct Camera {
+    view_pos: vec4<f32>;
+    view_proj: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var<uniform> camera: Camera;
+
+struct Grid {
+    inverse_view_proj: mat4x4<f32>;
+};
+[[group(1), binding(0)]]
+var<uniform> grid: Grid;
+
+let GRID_COLOR: vec3<f32> = vec3<f32>(0.5, 0.5, 0.5);
+let X_AXIS_COLOR: vec3<f32> = vec3<f32>(0.8, 0.2, 0.2);
+let Z_AXIS_COLOR: vec3<f32> = vec3<f32>(0.2, 0.3, 0.8);
+let FADE_DISTANCE: f32 = 40.0;
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] ndc: vec2<f32>;
+};
+
+// Fullscreen triangle that also hands its position on for unprojecting
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] index: u32) -> VertexOutput {
+    let ndc = fullscreen_ndc(index);
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+    out.ndc = ndc;
+
+    return out;
+}
+
+fn unproject(ndc: vec2<f32>, depth: f32) -> vec3<f32> {
+    let point = grid.inverse_view_proj * vec4<f32>(ndc, depth, 1.0);
+
+    return point.xyz / point.w;
+}
+
+struct FragmentOutput {
+    [[location(0)]] color: vec4<f32>;
+    [[builtin(frag_depth)]] depth: f32;
+};
+
+// Finds where the pixel's view ray meets y = 0 and draws unit lines there, a pixel or so wide
+// at any distance, fading out towards the horizon
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let near = unproject(in.ndc, 0.0);
+    let far = unproject(in.ndc, 1.0);
+    let t = -near.y / (far.y - near.y);
+
+    if (t < 0.0 || t > 1.0) {
+        discard;
+    }
+
+    let position = near + (far - near) * t;
+    let coord = position.xz;
+    let width = fwidth(coord);
+    let distance_to_line = abs(fract(coord - 0.5) - 0.5) / width;
+    let line = 1.0 - min(min(distance_to_line.x, distance_to_line.y), 1.0);
+    let axis = abs(coord) / width;
+    var color = GRID_COLOR;
+
+    if (axis.y < 1.0) {
+        color = X_AXIS_COLOR;
+    } else if (axis.x < 1.0) {
+        color = Z_AXIS_COLOR;
+    }
+
+    let fade = 1.0 - clamp(length(position - camera.view_pos.xyz) / FADE_DISTANCE, 0.0, 1.0);
+    let clip = camera.view_proj * vec4<f32>(position, 1.0);
+
+    var out: FragmentOutput;
+    out.color = vec4<f32>(color, line * fade);
+    out.depth = clip.z / clip.w;
+
+    return out;
+}
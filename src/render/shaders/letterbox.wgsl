@@ -0,0 +1,5 @@
+// Drawn over the fullscreen triangle, cut down to each bar by the scissor rect
+[[stage(fragment)]]
+fn fs_main() -> [[location(0)]] vec4<f32> {
+    return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+}
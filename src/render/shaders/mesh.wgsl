@@ -0,0 +1,14 @@
+// The per mesh uniform, prepended to the shaders that draw model meshes
+struct Mesh {
+    color: vec4<f32>;
+    offset: vec3<f32>;
+    color_source: u32;
+    transform: mat4x4<f32>;
+    emissive: vec3<f32>;
+};
+
+// Places a vertex within its model, by the mesh's own transform and then its explode offset
+fn mesh_position(placement: Mesh, position: vec3<f32>) -> vec3<f32> {
+    return (placement.transform * vec4<f32>(position, 1.0)).xyz + placement.offset;
+}
+
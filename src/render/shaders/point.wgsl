@@ -0,0 +1,68 @@
+struct Camera {
+    view_pos: vec4<f32>;
+    view_proj: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var<uniform> camera: Camera;
+
+struct Point {
+    size: f32;
+    padding: f32;
+    viewport: vec2<f32>;
+};
+[[group(1), binding(0)]]
+var<uniform> point: Point;
+
+[[group(2), binding(0)]]
+var<uniform> mesh: Mesh;
+
+struct VertexInput {
+    [[location(0)]] position: vec3<f32>;
+    [[location(2)]] color: vec4<f32>;
+};
+struct InstanceInput {
+    [[location(3)]] model_matrix_0: vec4<f32>;
+    [[location(4)]] model_matrix_1: vec4<f32>;
+    [[location(5)]] model_matrix_2: vec4<f32>;
+    [[location(6)]] model_matrix_3: vec4<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] color: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(
+    [[builtin(vertex_index)]] vertex_index: u32,
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    let center = camera.view_proj * model_matrix * vec4<f32>(mesh_position(mesh, model.position), 1.0);
+    let offset = corners[vertex_index] * point.size / point.viewport * center.w;
+
+    var out: VertexOutput;
+    out.clip_position = center + vec4<f32>(offset, 0.0, 0.0);
+    out.color = model.color;
+
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return in.color;
+}
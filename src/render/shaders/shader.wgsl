@@ -1,10 +1,23 @@
 // Vertex shader
 
-struct Ambient {
-    color: vec4<f32>;
+struct Shading {
+    ambient: vec4<f32>;
+    specular_color: vec3<f32>;
+    shininess: f32;
+    specular_strength: f32;
+    metallic: f32;
+    roughness: f32;
+    fog_density: f32;
+    fog_color: vec4<f32>;
+    fog_start: f32;
+    fog_end: f32;
+    ao_strength: f32;
+    wrap: f32;
+    clip_plane: vec4<f32>;
+    exposure: f32;
 };
 [[group(0), binding(0)]]
-var<uniform> ambient: Ambient;
+var<uniform> shading: Shading;
 
 struct Camera {
     view_pos: vec4<f32>;
@@ -20,6 +33,9 @@ struct Light {
 [[group(2), binding(0)]]
 var<uniform> light: Light;
 
+[[group(3), binding(0)]]
+var<uniform> mesh: Mesh;
+
 struct VertexInput {
     [[location(0)]] position: vec3<f32>;
     [[location(1)]] normal: vec3<f32>;
@@ -40,13 +56,12 @@ struct VertexOutput {
     [[location(0)]] normal: vec3<f32>;
     [[location(1)]] position: vec3<f32>;
     [[location(2)]] color: vec4<f32>;
+    [[location(3), interpolate(flat)]] instance: u32;
 };
 
-[[stage(vertex)]]
-fn vs_main(
-    model: VertexInput,
-    instance: InstanceInput,
-) -> VertexOutput {
+// Places a vertex in the world, then moves it inflate times its distance from the camera along
+// its normal, which keeps an inflated shell the same thickness on screen
+fn model_vertex(instance_index: u32, model: VertexInput, instance: InstanceInput, inflate: f32) -> VertexOutput {
     let model_matrix = mat4x4<f32>(
         instance.model_matrix_0,
         instance.model_matrix_1,
@@ -59,31 +74,238 @@ fn vs_main(
         instance.normal_matrix_2,
     );
 
-    let world_normal = normalize(normal_matrix * model.normal);
-    let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+    let mesh_normal_matrix = mat3x3<f32>(mesh.transform[0].xyz, mesh.transform[1].xyz, mesh.transform[2].xyz);
+    let world_normal = normalize(normal_matrix * mesh_normal_matrix * model.normal);
+    var world_position = model_matrix * vec4<f32>(mesh_position(mesh, model.position), 1.0);
+    let camera_distance = length(camera.view_pos.xyz - world_position.xyz);
+    world_position = vec4<f32>(world_position.xyz + world_normal * inflate * camera_distance, 1.0);
 
     var out: VertexOutput;
     out.clip_position = camera.view_proj * world_position;
     out.normal = world_normal;
-    out.position = model.position;
+    out.position = world_position.xyz;
     out.color = model.color;
+    out.instance = instance_index;
 
     return out;
 }
 
+[[stage(vertex)]]
+fn vs_main(
+    [[builtin(instance_index)]] instance_index: u32,
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    return model_vertex(instance_index, model, instance, 0.0);
+}
+
+let OUTLINE_WIDTH: f32 = 0.006;
+
+[[stage(vertex)]]
+fn vs_outline(
+    [[builtin(instance_index)]] instance_index: u32,
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    return model_vertex(instance_index, model, instance, OUTLINE_WIDTH);
+}
+
 // Fragment shader
 
+// Vertex colors, the mesh's material color, or the two multiplied, as picked by color_source
+fn base_color(color: vec4<f32>) -> vec3<f32> {
+    if (mesh.color_source == 1u) {
+        return mesh.color.xyz;
+    }
+    if (mesh.color_source == 2u) {
+        return mesh.color.xyz * color.xyz;
+    }
+
+    return color.xyz;
+}
+
+// Darkens surfaces as they turn away from up, a cheap stand-in for occlusion
+fn fake_ao(normal: vec3<f32>) -> f32 {
+    let up = dot(normalize(normal), vec3<f32>(0.0, 1.0, 0.0)) * 0.5 + 0.5;
+
+    return mix(1.0 - shading.ao_strength, 1.0, up);
+}
+
+// Half-Lambert style wrap, light reaches past the terminator as wrap goes from 0 to 1
+fn wrap_diffuse(n_dot_l: f32) -> f32 {
+    return (n_dot_l + shading.wrap) / (1.0 + shading.wrap);
+}
+
+// Positions on the negative side of the clip plane are cut away, a zero plane keeps everything
+fn clipped(position: vec3<f32>) -> bool {
+    return dot(shading.clip_plane.xyz, position) + shading.clip_plane.w < 0.0;
+}
+
+fn apply_exposure(color: vec3<f32>) -> vec3<f32> {
+    return color * exp2(shading.exposure);
+}
+
+// Exponential fog when a density is set, otherwise linear between start and end
+fn apply_fog(color: vec3<f32>, position: vec3<f32>) -> vec3<f32> {
+    let distance = length(position - camera.view_pos.xyz);
+    var visibility = 1.0;
+
+    if (shading.fog_density > 0.0) {
+        visibility = exp(-shading.fog_density * distance);
+    } else if (shading.fog_end > shading.fog_start) {
+        visibility = clamp((shading.fog_end - distance) / (shading.fog_end - shading.fog_start), 0.0, 1.0);
+    }
+
+    return mix(shading.fog_color.xyz, color, vec3<f32>(visibility));
+}
+
+// Blinn-Phong lighting of one surface point, shared by fs_main and the deferred lighting pass
+fn phong(position: vec3<f32>, normal: vec3<f32>, albedo: vec3<f32>, emissive: vec3<f32>) -> vec3<f32> {
+    let ambient_strength = 0.1;
+    let ambient_color = shading.ambient.xyz * ambient_strength;
+
+    let light_dir = normalize(light.position - position);
+
+    let diffuse_strength = dot(normal, light_dir);
+    let diffuse_color = albedo * wrap_diffuse(diffuse_strength);
+
+    let view_dir = normalize(camera.view_pos.xyz - position);
+    let half_dir = normalize(view_dir + light_dir);
+    let specular_strength = pow(max(dot(normal, half_dir), 0.0), shading.shininess) * shading.specular_strength;
+    let specular_color = shading.specular_color * light.color * select(0.0, specular_strength, diffuse_strength > 0.0);
+
+    return ambient_color + diffuse_color + specular_color + emissive;
+}
+
 [[stage(fragment)]]
 fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-    let ambient_strength = 0.1;
-    let ambient_color = ambient.color.xyz * ambient_strength;
+    if (clipped(in.position)) {
+        discard;
+    }
 
+    let albedo = base_color(in.color) * fake_ao(in.normal);
+    let color = phong(in.position, in.normal, albedo, mesh.emissive);
+
+    return vec4<f32>(apply_fog(apply_exposure(color), in.position), in.color.a);
+}
+
+let PI: f32 = 3.14159265;
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+
+    return a2 / (PI * d * d);
+}
+
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    let view_term = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let light_term = n_dot_l / (n_dot_l * (1.0 - k) + k);
+
+    return view_term * light_term;
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (vec3<f32>(1.0) - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+[[stage(fragment)]]
+fn fs_pbr(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    if (clipped(in.position)) {
+        discard;
+    }
+
+    let albedo = base_color(in.color) * fake_ao(in.normal);
+    let roughness = clamp(shading.roughness, 0.04, 1.0);
+
+    let normal = normalize(in.normal);
+    let view_dir = normalize(camera.view_pos.xyz - in.position);
     let light_dir = normalize(light.position - in.position);
+    let half_dir = normalize(view_dir + light_dir);
+
+    let n_dot_l = max(dot(normal, light_dir), 0.0);
+    let n_dot_v = max(dot(normal, view_dir), 0.0001);
+    let n_dot_h = max(dot(normal, half_dir), 0.0);
+
+    let f0 = mix(vec3<f32>(0.04), albedo, vec3<f32>(shading.metallic));
+    let fresnel = fresnel_schlick(max(dot(half_dir, view_dir), 0.0), f0);
+    let specular = distribution_ggx(n_dot_h, roughness) * geometry_smith(n_dot_v, n_dot_l, roughness) * fresnel
+        / (4.0 * n_dot_v * max(n_dot_l, 0.0001));
+    let diffuse = (vec3<f32>(1.0) - fresnel) * (1.0 - shading.metallic) * albedo / PI;
 
-    let diffuse_strength = dot(in.normal, light_dir);
-    let diffuse_color = in.color.zyx * diffuse_strength;
+    let ambient_color = shading.ambient.xyz * 0.1 * albedo;
+    let color = ambient_color + (diffuse + specular) * light.color * n_dot_l + mesh.emissive;
+
+    return vec4<f32>(apply_fog(apply_exposure(color), in.position), in.color.a);
+}
+
+let XRAY_COLOR: vec4<f32> = vec4<f32>(0.3, 0.7, 1.0, 0.35);
+
+[[stage(fragment)]]
+fn fs_xray(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    if (clipped(in.position)) {
+        discard;
+    }
 
-    let color = ambient_color + diffuse_color;
+    let view_dir = normalize(camera.view_pos.xyz - in.position);
+    let rim = 1.0 - abs(dot(normalize(in.normal), view_dir));
+
+    return vec4<f32>(XRAY_COLOR.xyz, XRAY_COLOR.w * (0.5 + 0.5 * rim));
+}
+
+let FRONT_COLOR: vec3<f32> = vec3<f32>(0.1, 0.8, 0.1);
+let BACK_COLOR: vec3<f32> = vec3<f32>(0.8, 0.1, 0.1);
+
+// Debug view of winding, drawn without culling so back faces show up
+[[stage(fragment)]]
+fn fs_facing(in: VertexOutput, [[builtin(front_facing)]] front_facing: bool) -> [[location(0)]] vec4<f32> {
+    if (clipped(in.position)) {
+        discard;
+    }
+
+    let light_dir = normalize(light.position - in.position);
+    let shade = 0.4 + 0.6 * abs(dot(normalize(in.normal), light_dir));
+
+    return vec4<f32>(select(BACK_COLOR, FRONT_COLOR, front_facing) * shade, 1.0);
+}
+
+let OUTLINE_COLOR: vec3<f32> = vec3<f32>(1.0, 0.6, 0.1);
+
+// Back faces of the inflated selection, which only show as a rim around the model
+[[stage(fragment)]]
+fn fs_outline(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    if (clipped(in.position)) {
+        discard;
+    }
+
+    return vec4<f32>(OUTLINE_COLOR, 1.0);
+}
+
+// Spreads neighbouring instance indices far apart in hue
+fn index_color(index: u32) -> vec3<f32> {
+    var hash = index * 747796405u + 2891336453u;
+    hash = ((hash >> ((hash >> 28u) + 4u)) ^ hash) * 277803737u;
+    hash = (hash >> 22u) ^ hash;
+
+    return vec3<f32>(
+        f32(hash & 255u),
+        f32((hash >> 8u) & 255u),
+        f32((hash >> 16u) & 255u),
+    ) / 255.0;
+}
+
+// Debug view of the instance buffer, each instance gets its own flat color
+[[stage(fragment)]]
+fn fs_instance(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    if (clipped(in.position)) {
+        discard;
+    }
+
+    let light_dir = normalize(light.position - in.position);
+    let shade = 0.4 + 0.6 * max(dot(normalize(in.normal), light_dir), 0.0);
 
-    return vec4<f32>(color, in.color.a);
+    return vec4<f32>(index_color(in.instance) * shade, 1.0);
 }
@@ -0,0 +1,76 @@
+struct Camera {
+    view_pos: vec4<f32>;
+    view_proj: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var<uniform> camera: Camera;
+
+struct Wire {
+    width: f32;
+    padding: f32;
+    viewport: vec2<f32>;
+};
+[[group(1), binding(0)]]
+var<uniform> wire: Wire;
+
+[[group(2), binding(0)]]
+var<uniform> mesh: Mesh;
+
+struct SegmentInput {
+    [[location(0)]] start: vec3<f32>;
+    [[location(2)]] color: vec4<f32>;
+    [[location(10)]] end: vec3<f32>;
+};
+struct InstanceInput {
+    [[location(3)]] model_matrix_0: vec4<f32>;
+    [[location(4)]] model_matrix_1: vec4<f32>;
+    [[location(5)]] model_matrix_2: vec4<f32>;
+    [[location(6)]] model_matrix_3: vec4<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] color: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(
+    [[builtin(vertex_index)]] vertex_index: u32,
+    segment: SegmentInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    // x picks the end of the segment, y the side of it
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    let corner = corners[vertex_index];
+    let start = camera.view_proj * model_matrix * vec4<f32>(mesh_position(mesh, segment.start), 1.0);
+    let end = camera.view_proj * model_matrix * vec4<f32>(mesh_position(mesh, segment.end), 1.0);
+    let along = (end.xy / end.w - start.xy / start.w) * wire.viewport;
+    let direction = select(vec2<f32>(1.0, 0.0), normalize(along), length(along) > 0.0);
+    let side = vec2<f32>(-direction.y, direction.x);
+    let center = select(start, end, corner.x > 0.5);
+    let offset = side * corner.y * wire.width / wire.viewport * center.w;
+
+    var out: VertexOutput;
+    out.clip_position = center + vec4<f32>(offset, 0.0, 0.0);
+    out.color = segment.color;
+
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return in.color;
+}
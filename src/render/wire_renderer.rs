@@ -0,0 +1,126 @@
+use std::ops::Range;
+
+use crate::{
+  instance::InstanceRaw,
+  mesh::{Mesh, MeshVertex},
+  render::{create_render_pipeline, PipelineOptions},
+};
+
+const VERTEX_SIZE: wgpu::BufferAddress = std::mem::size_of::<MeshVertex>() as wgpu::BufferAddress;
+
+// The start point and color of a segment from its first vertex, and the end point from the second
+const SEGMENT_ATTRIBUTES: [wgpu::VertexAttribute; 3] = [
+  wgpu::VertexAttribute {
+    offset: 0,
+    shader_location: 0,
+    format: wgpu::VertexFormat::Float32x3,
+  },
+  wgpu::VertexAttribute {
+    offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+    shader_location: 2,
+    format: wgpu::VertexFormat::Float32x4,
+  },
+  wgpu::VertexAttribute {
+    offset: VERTEX_SIZE,
+    shader_location: 10,
+    format: wgpu::VertexFormat::Float32x3,
+  },
+];
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct WireUniform {
+  pub width: f32,
+  pub _width_padding: u32,
+  pub viewport: [f32; 2],
+}
+
+// Draws unwelded line list meshes, where vertices 2i and 2i + 1 are segment i, with each
+// segment expanded into a screen facing quad a fixed number of pixels wide. Like points,
+// the model transform is read through a zero stride slice of the instance buffer.
+pub struct WireRenderer {
+  render_pipeline: wgpu::RenderPipeline,
+}
+
+impl WireRenderer {
+  pub fn new(
+    device: &wgpu::Device,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+    depth_format: Option<wgpu::TextureFormat>,
+    vertex_layouts: &[wgpu::VertexBufferLayout],
+  ) -> Self {
+    let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("Wire Pipeline Layout"),
+      bind_group_layouts,
+      push_constant_ranges: &[],
+    });
+    let vertex_layouts = [
+      wgpu::VertexBufferLayout {
+        array_stride: VERTEX_SIZE * 2,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &SEGMENT_ATTRIBUTES,
+      },
+      wgpu::VertexBufferLayout {
+        array_stride: 0,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        ..vertex_layouts[1].clone()
+      },
+    ];
+    let render_pipeline = {
+      let shader = wgpu::ShaderModuleDescriptor {
+        label: Some("Wire Shader"),
+        source: wgpu::ShaderSource::Wgsl(
+          concat!(include_str!("shaders/mesh.wgsl"), include_str!("shaders/wire.wgsl")).into()
+        ),
+      };
+
+      create_render_pipeline(
+        device,
+        &render_pipeline_layout,
+        format,
+        depth_format,
+        &vertex_layouts,
+        shader,
+        PipelineOptions {
+          cull_mode: None,
+          depth_compare: wgpu::CompareFunction::LessEqual,
+          ..PipelineOptions::new("Wire Render Pipeline")
+        },
+      )
+    };
+
+    Self { render_pipeline }
+  }
+
+  pub fn render<'a, M>(
+    &'a self,
+    render_pass: &mut wgpu::RenderPass<'a>,
+    meshes: M,
+    instance_buffer: &'a wgpu::Buffer,
+    instances: Range<u32>,
+    camera_bind_group: &'a wgpu::BindGroup,
+    wire_bind_group: &'a wgpu::BindGroup,
+  )
+  where
+    M: Iterator<Item = &'a Mesh>,
+  {
+    let instance_size = std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress;
+
+    render_pass.set_pipeline(&self.render_pipeline);
+    render_pass.set_bind_group(0, camera_bind_group, &[]);
+    render_pass.set_bind_group(1, wire_bind_group, &[]);
+
+    for mesh in meshes.filter(|mesh| mesh.num_vertices >= 2) {
+      render_pass.set_bind_group(2, &mesh.uniform.bind_group, &[]);
+      render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+
+      for instance in instances.clone() {
+        let offset = instance as wgpu::BufferAddress * instance_size;
+
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(offset..offset + instance_size));
+        render_pass.draw(0..6, 0..mesh.num_vertices / 2);
+      }
+    }
+  }
+}
@@ -0,0 +1,175 @@
+use anyhow::{Context, Result};
+use cgmath::{Deg, Euler, Quaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+use crate::instance::Instance;
+
+// Declarative startup scene, read from JSON
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scene {
+  pub background: Option<[f32; 3]>,
+  pub camera: Option<SceneCamera>,
+  pub light: Option<SceneLight>,
+  pub models: Vec<SceneModel>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneCamera {
+  pub position: [f32; 3],
+  #[serde(default)]
+  pub target: [f32; 3],
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneLight {
+  pub color: Option<[f32; 3]>,
+  pub position: Option<[f32; 3]>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneModel {
+  // Without instances the model shares the instance set from the command line
+  #[serde(default)]
+  pub instances: Vec<SceneInstance>,
+  pub source: ModelSource,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum ModelSource {
+  Cube { size: f32 },
+  Fence {
+    posts: u32,
+    spacing: f32,
+    width: f32,
+    length: f32,
+    height: f32,
+    #[serde(default = "default_rails")]
+    rails: bool,
+  },
+  File { path: PathBuf },
+  Heightmap {
+    path: PathBuf,
+    size: f32,
+    #[serde(default = "default_subdivisions")]
+    subdivisions: u32,
+    #[serde(default = "default_scale")]
+    scale: f32,
+  },
+  House { width: f32, length: f32, height: f32 },
+  // Several files joined into one model
+  Merged { parts: Vec<ScenePart> },
+  Plane {
+    size: f32,
+    #[serde(default = "default_subdivisions")]
+    subdivisions: u32,
+  },
+  // Rows and z_size default to the columns and x_size, which scenes used to give as count and size
+  Surface {
+    #[serde(alias = "count")]
+    cols: u32,
+    #[serde(default)]
+    rows: Option<u32>,
+    #[serde(alias = "size")]
+    x_size: f32,
+    #[serde(default)]
+    z_size: Option<f32>,
+    max: f32,
+    #[serde(default)]
+    quad_edges: bool,
+  },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScenePart {
+  #[serde(default)]
+  pub offset: [f32; 3],
+  pub path: PathBuf,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneInstance {
+  #[serde(default)]
+  pub position: [f32; 3],
+  // Euler angles in degrees, applied x then y then z
+  #[serde(default)]
+  pub rotation: [f32; 3],
+  #[serde(default = "default_scale")]
+  pub scale: f32,
+}
+
+impl Scene {
+  // Model paths are taken relative to the scene file
+  pub fn load(path: &Path) -> Result<Self> {
+    let text = fs::read_to_string(path).with_context(|| format!("Couldn't read scene {}", path.display()))?;
+    let mut scene: Scene = serde_json::from_str(&text)
+      .with_context(|| format!("Couldn't parse scene {}", path.display()))?;
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+
+    for model in &mut scene.models {
+      match &mut model.source {
+        ModelSource::File { path } | ModelSource::Heightmap { path, .. } => *path = base.join(&*path),
+        ModelSource::Merged { parts } => {
+          for part in parts {
+            part.path = base.join(&part.path);
+          }
+        }
+        _ => {}
+      }
+    }
+
+    Ok(scene)
+  }
+
+  pub fn save(&self, path: &Path) -> Result<()> {
+    let text = serde_json::to_string_pretty(self)?;
+
+    fs::write(path, text).with_context(|| format!("Couldn't write scene {}", path.display()))
+  }
+}
+
+impl From<&SceneInstance> for Instance {
+  fn from(instance: &SceneInstance) -> Self {
+    let [x, y, z] = instance.rotation;
+
+    Instance {
+      position: Vector3::from(instance.position),
+      rotation: Quaternion::from(Euler::new(Deg(x), Deg(y), Deg(z))),
+      scale: instance.scale,
+    }
+  }
+}
+
+impl From<&Instance> for SceneInstance {
+  fn from(instance: &Instance) -> Self {
+    let rotation = Euler::from(instance.rotation);
+
+    SceneInstance {
+      position: instance.position.into(),
+      rotation: [Deg::from(rotation.x).0, Deg::from(rotation.y).0, Deg::from(rotation.z).0],
+      scale: instance.scale,
+    }
+  }
+}
+
+fn default_rails() -> bool {
+  true
+}
+
+fn default_scale() -> f32 {
+  1.0
+}
+
+fn default_subdivisions() -> u32 {
+  1
+}
@@ -0,0 +1,57 @@
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ShadingModel {
+    Phong,
+    Pbr,
+}
+
+impl ShadingModel {
+    pub fn fragment_entry(&self) -> &'static str {
+        match self {
+            ShadingModel::Phong => "fs_main",
+            ShadingModel::Pbr => "fs_pbr",
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShadingUniform {
+    pub ambient: [f32; 4],
+    pub specular_color: [f32; 3],
+    pub shininess: f32,
+    pub specular_strength: f32,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub fog_density: f32,
+    pub fog_color: [f32; 4],
+    pub fog_start: f32,
+    pub fog_end: f32,
+    pub ao_strength: f32,
+    pub wrap: f32,
+    pub clip_plane: [f32; 4],
+    // In stops, the lit color is scaled by 2^exposure
+    pub exposure: f32,
+    pub _padding: [u32; 3],
+}
+
+impl Default for ShadingUniform {
+    fn default() -> Self {
+        Self {
+            ambient: [0.3, 0.3, 0.3, 1.0],
+            specular_color: [1.0, 1.0, 1.0],
+            shininess: 32.0,
+            specular_strength: 0.5,
+            metallic: 0.0,
+            roughness: 0.5,
+            fog_density: 0.0,
+            fog_color: [0.3, 0.3, 0.3, 1.0],
+            fog_start: 0.0,
+            fog_end: 0.0,
+            ao_strength: 0.0,
+            wrap: 0.0,
+            clip_plane: [0.0; 4],
+            exposure: 0.0,
+            _padding: [0; 3],
+        }
+    }
+}
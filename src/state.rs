@@ -1,60 +1,137 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use cgmath::{
+  Angle,
   Deg,
+  EuclideanSpace,
   InnerSpace,
-  Quaternion,
-  Rotation3,
+  Point3,
+  Rad,
+  Vector2,
   Vector3,
-  Zero,
 };
+use std::path::{Path, PathBuf};
 use winit::{
-  event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode},
+  event::{DeviceEvent, ElementState, KeyboardInput, ModifiersState, VirtualKeyCode, WindowEvent},
   window::Window,
 };
-use wgpu::util::DeviceExt;
 
 use crate::{
-  camera::{CameraController, CameraRig, OrbitCamera, OrbitCameraController},
-  instance::Instance,
-  model::{Model, ModelPrimitive},
-  render::Renderer,
+  bounds::Aabb,
+  camera::{Camera, CameraController, CameraPath, CameraRig, DollyZoom, OrbitCamera, OrbitCameraController, Turntable, ZoomMode},
+  capture::{self, FrameRecorder},
+  instance::{self, Instance, Layout},
+  light,
+  model::{ImportOptions, Model, ModelPrimitive},
+  render::{Grid, RenderOptions, Renderer},
+  scene::{ModelSource, Scene, SceneCamera, SceneInstance, SceneLight, SceneModel, ScenePart},
+  shading::ShadingModel,
+  stats::FrameStats,
+  texture::Texture,
+  ui::Ui,
 };
 
-const NUM_INSTANCES_PER_ROW: u32 = 1;
+const CLIP_PLANE_STEP: f32 = 0.1;
+const EXPLODE_STEP: f32 = 0.1;
+const EXPOSURE_STEP: f32 = 0.25;
+const MESH_KEYS: [VirtualKeyCode; 9] = [
+  VirtualKeyCode::Key1,
+  VirtualKeyCode::Key2,
+  VirtualKeyCode::Key3,
+  VirtualKeyCode::Key4,
+  VirtualKeyCode::Key5,
+  VirtualKeyCode::Key6,
+  VirtualKeyCode::Key7,
+  VirtualKeyCode::Key8,
+  VirtualKeyCode::Key9,
+];
+const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+
+struct KeyBinding {
+  action: &'static str,
+  keys: &'static str,
+  state: Option<fn(&State) -> bool>,
+}
+
+// Every runtime key, listed by the help overlay. Keep in step with State::input and the camera controller.
+const KEY_BINDINGS: &[KeyBinding] = &[
+  KeyBinding { action: "Move forward / back", keys: "W / S", state: None },
+  KeyBinding { action: "Move left / right", keys: "A / D", state: None },
+  KeyBinding { action: "Move up / down", keys: "Space / Shift", state: None },
+  KeyBinding { action: "Orbit", keys: "Arrows", state: None },
+  KeyBinding { action: "Toggle bounding boxes", keys: "B", state: Some(|state| state.renderer.rendering_bounds()) },
+  KeyBinding { action: "Explode / assemble meshes", keys: "] / [", state: None },
+  KeyBinding { action: "Raise / lower exposure", keys: "+ / -", state: None },
+  KeyBinding { action: "Slide the clip plane along / against its normal", keys: ". / ,", state: None },
+  KeyBinding { action: "Nudge light along x / y / z", keys: "J L / U O / I K", state: None },
+  KeyBinding { action: "Show light", keys: "V", state: Some(|state| state.renderer.rendering_light()) },
+  KeyBinding { action: "Rotate light", keys: "R", state: Some(|state| state.renderer.rotating_light()) },
+  KeyBinding { action: "Show / hide mesh 1 to 9", keys: "Ctrl+1..9", state: None },
+  KeyBinding { action: "Top-down minimap", keys: "M", state: Some(|state| state.renderer.rendering_minimap()) },
+  KeyBinding { action: "Axes gizmo", keys: "G", state: Some(|state| state.renderer.rendering_axes()) },
+  KeyBinding { action: "Freeze the camera frustum", keys: "F", state: Some(|state| state.renderer.freezing_frustum()) },
+  KeyBinding { action: "Save screenshot", keys: "P", state: None },
+  KeyBinding { action: "Save the scene as JSON", keys: "Ctrl+S", state: None },
+  KeyBinding { action: "Rebuild the last added model", keys: "Enter", state: None },
+  KeyBinding { action: "Orbit the next instance", keys: "Tab", state: None },
+  KeyBinding { action: "Clear the instance selection", keys: "Backspace", state: None },
+  KeyBinding { action: "Log the cursor's ground position", keys: "F8", state: Some(|state| state.show_ground_cursor) },
+  KeyBinding { action: "Cull back faces", keys: "X", state: Some(|state| state.renderer.culling_backfaces()) },
+  KeyBinding { action: "Color front / back faces", keys: "F9", state: Some(|state| state.renderer.rendering_facing()) },
+  KeyBinding { action: "Color instances by index", keys: "F10", state: Some(|state| state.renderer.rendering_instance_colors()) },
+  KeyBinding { action: "Show vertex normals", keys: "F11", state: Some(|state| state.renderer.rendering_normals()) },
+  KeyBinding { action: "Show this help", keys: "H / F1", state: Some(|state| state.show_help) },
+  KeyBinding { action: "Quit", keys: "Escape", state: None },
+];
 
 pub struct State {
+  adapter_info: wgpu::AdapterInfo,
+  camera_path: Option<CameraPath>,
   camera_rig: CameraRig<OrbitCamera, OrbitCameraController>,
   config: wgpu::SurfaceConfiguration,
+  control_held: bool,
   cube_model: Model,
+  cursor_position: Vector2<f32>,
   device: wgpu::Device,
-  instance_buffer: wgpu::Buffer,
+  dolly_zoom: Option<DollyZoom>,
+  draw_limit: Option<u32>,
+  explode: f32,
+  import_options: ImportOptions,
+  instances: Vec<Instance>,
+  // The light color before light_intensity scales it, as set by the CLI, the sun or a scene
+  light_color: [f32; 3],
+  light_intensity: f32,
+  light_step: f32,
+  max_dt: std::time::Duration,
+  // The --mesh-color and --mesh-emissive overrides, kept so a rebuilt model can get them back
+  mesh_colors: Vec<(usize, [f32; 3])>,
+  mesh_emissive: Vec<(usize, [f32; 3])>,
+  // Set by a zero size resize, which leaves size at the last drawable one
+  minimized: bool,
   mouse_pressed: bool,
+  normal_length: f32,
+  // What each added model was built from and where it sits in models, oldest first
+  model_sources: Vec<(usize, ModelSource)>,
   models: Vec<Model>,
+  pan_pressed: bool,
   queue: wgpu::Queue,
+  recorder: Option<FrameRecorder>,
   renderer: Renderer,
+  screenshot_dir: PathBuf,
+  selected_instance: Option<usize>,
+  show_ground_cursor: bool,
+  show_help: bool,
   pub size: winit::dpi::PhysicalSize<u32>,
+  stats: Option<FrameStats>,
   surface: wgpu::Surface,
+  turntable: Option<Turntable>,
+  ui: Option<Ui>,
 }
 
 impl State {
-  pub async fn new(window: &Window) -> Self {
+  pub async fn new(window: &Window, options: RenderOptions) -> Self {
     let instance = wgpu::Instance::new(wgpu::Backends::all());
     let surface = unsafe { instance.create_surface(window) };
-    let adapter = instance.request_adapter(
-      &wgpu::RequestAdapterOptions {
-        power_preference: wgpu::PowerPreference::default(),
-        compatible_surface: Some(&surface),
-        force_fallback_adapter: false,
-      },
-    ).await.unwrap();
-    let (device, queue) = adapter.request_device(
-      &wgpu::DeviceDescriptor {
-        features: wgpu::Features::empty(),
-        limits: wgpu::Limits::default(),
-        label: None,
-      },
-      None,
-    ).await.unwrap();
+    let (adapter, device, queue) = request_device(&instance, Some(&surface)).await.unwrap();
     let size = window.inner_size();
     let config = wgpu::SurfaceConfiguration {
       usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -67,80 +144,307 @@ impl State {
     surface.configure(&device, &config);
 
     let camera_rig = CameraRig::new((0.0, 5.0, 10.0));
-    let mut renderer = Renderer::new(&device, &config);
+    let options = RenderOptions {
+      depth_format: options.depth_format.map(|format| Texture::select_depth_format(&adapter, format)),
+      ..options
+    };
+    let mut renderer = Renderer::new(&device, &config, &options);
 
     renderer.update_camera_uniform(&camera_rig.camera);
 
-    let res_dir = std::path::Path::new(env!("OUT_DIR")).join("res");
-    let cube_model = Model::load(
-      &device,
-      res_dir.join("cube.obj"),
-    ).unwrap();
-
-    let instances = (0..NUM_INSTANCES_PER_ROW).flat_map(|z| {
-      (0..NUM_INSTANCES_PER_ROW).map(move |x| {
-        let position = Vector3 { x: x as f32, y: 0.0, z: z as f32 };
-        let rotation = if position.is_zero() {
-          Quaternion::from_axis_angle(
-            Vector3::unit_z(),
-            Deg(0.0),
-          )
-        } else {
-          Quaternion::from_axis_angle(position.normalize(), Deg(45.0))
-        };
-
-        Instance {
-          position,
-          rotation,
-        }
-      })
-    }).collect::<Vec<_>>();
-    let instance_data = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
-    let instance_buffer = device.create_buffer_init(
-      &wgpu::util::BufferInitDescriptor {
-        label: Some("Instance Buffer"),
-        contents: bytemuck::cast_slice(&instance_data),
-        usage: wgpu::BufferUsages::VERTEX,
-      }
-    );
+    // Stands in for the light until the bundled cube is loaded, matching its 2 unit extent
+    let cube_model = Model::cube(&device, 2.0);
+
+    let instances = instance::layout(Layout::Grid, 1, 1.0);
 
     Self {
+      adapter_info: adapter.get_info(),
+      camera_path: None,
       camera_rig,
       config,
+      control_held: false,
       cube_model,
+      cursor_position: Vector2::new(0.0, 0.0),
       device,
-      instance_buffer,
+      dolly_zoom: None,
+      draw_limit: None,
+      explode: 0.0,
+      import_options: ImportOptions::default(),
+      instances,
+      light_color: renderer.light_color(),
+      light_intensity: 1.0,
+      light_step: 0.25,
+      max_dt: std::time::Duration::from_millis(100),
+      mesh_colors: Vec::new(),
+      mesh_emissive: Vec::new(),
+      minimized: false,
+      model_sources: Vec::new(),
       models: Vec::<Model>::new(),
       mouse_pressed: false,
+      normal_length: 0.1,
+      pan_pressed: false,
       queue,
+      recorder: None,
       renderer,
+      screenshot_dir: PathBuf::from("."),
+      selected_instance: None,
+      show_ground_cursor: false,
+      show_help: false,
       size,
+      stats: None,
       surface,
+      turntable: None,
+      ui: None,
     }
   }
 
+  pub fn add_fence(&mut self, posts: u32, spacing: f32, width: f32, length: f32, height: f32, rails: bool) {
+    let model = Model::fence(&self.device, posts, spacing, width, length, height, rails);
+
+    self.push_model(model);
+    self.remember_source(ModelSource::Fence { posts, spacing, width, length, height, rails });
+  }
+
+  pub fn add_heightmap(&mut self, path: &Path, size: f32, subdivisions: u32, scale: f32) -> Result<()> {
+    let source = ModelSource::Heightmap { path: path.to_path_buf(), size, subdivisions, scale };
+    let model = self.build_model(&source)?;
+
+    self.push_model(model);
+    self.remember_source(source);
+    Ok(())
+  }
+
   pub fn add_house(&mut self, width: f32, length: f32, height: f32) {
-    let model = Model::house(&self.device, width, length, height);
+    let model = Model::house(&self.device, &self.queue, width, length, height);
 
-    self.models.push(model);
+    self.push_model(model);
+    self.remember_source(ModelSource::House { width, length, height });
+  }
+
+  // A primitive with its own instance set, such as a forest of scattered copies
+  pub fn add_instanced_primitive(&mut self, primitive: ModelPrimitive, size: f32, transforms: Vec<Instance>) {
+    let model = Self::build_primitive(&self.device, &primitive, size);
+
+    self.push_instanced_model(model, transforms);
+    self.remember_source(Self::primitive_source(primitive, size));
+  }
+
+  pub fn add_merged(&mut self, paths: &[PathBuf]) -> Result<()> {
+    let parts = paths.iter().map(|path| ScenePart { offset: [0.0; 3], path: path.clone() }).collect();
+    let source = ModelSource::Merged { parts };
+    let model = self.build_model(&source)?;
+
+    self.push_model(model);
+    self.remember_source(source);
+    Ok(())
   }
 
   pub fn add_model_primitive(&mut self, primitive: ModelPrimitive, size: f32) {
-    let model = match primitive {
-      ModelPrimitive::Cube => Model::cube(&self.device, size),
-      ModelPrimitive::Plane => Model::plane(&self.device, size),
+    self.add_instanced_primitive(primitive, size, self.instances.clone());
+  }
+
+  // counts and sizes are [x, z]
+  pub fn add_surface(&mut self, counts: [u32; 2], sizes: [f32; 2], height_max: f32, quad_edges: bool) {
+    let model = Model::surface(&self.device, counts, sizes, height_max, quad_edges);
+    let [cols, rows] = counts;
+    let [x_size, z_size] = sizes;
+
+    self.push_model(model);
+    self.remember_source(ModelSource::Surface {
+      cols,
+      rows: Some(rows),
+      x_size,
+      z_size: Some(z_size),
+      max: height_max,
+      quad_edges,
+    });
+  }
+
+  fn apply_light_color(&mut self, color: [f32; 3]) {
+    self.light_color = color;
+    self.renderer.set_light_color(color.map(|channel| channel * self.light_intensity));
+  }
+
+  fn build_model(&self, source: &ModelSource) -> Result<Model> {
+    Ok(match source {
+      ModelSource::Cube { size } => Model::cube(&self.device, *size),
+      ModelSource::Fence { posts, spacing, width, length, height, rails } => {
+        Model::fence(&self.device, *posts, *spacing, *width, *length, *height, *rails)
+      }
+      ModelSource::File { path } => Model::load_with(&self.device, path, &self.import_options)?,
+      ModelSource::Heightmap { path, size, subdivisions, scale } => {
+        Model::heightmap(&self.device, path, *size, *subdivisions, *scale)?
+      }
+      ModelSource::House { width, length, height } => Model::house(&self.device, &self.queue, *width, *length, *height),
+      ModelSource::Merged { parts } => {
+        let files = parts.iter().map(|part| (&part.path, Vector3::from(part.offset))).collect::<Vec<_>>();
+
+        Model::load_many(&self.device, &files, &self.import_options)?
+      }
+      ModelSource::Plane { size, subdivisions } => Model::plane(&self.device, *size, *subdivisions),
+      ModelSource::Surface { cols, rows, x_size, z_size, max, quad_edges } => Model::surface(
+        &self.device,
+        [*cols, rows.unwrap_or(*cols)],
+        [*x_size, z_size.unwrap_or(*x_size)],
+        *max,
+        *quad_edges,
+      ),
+    })
+  }
+
+  fn build_primitive(device: &wgpu::Device, primitive: &ModelPrimitive, size: f32) -> Model {
+    match primitive {
+      ModelPrimitive::Cube => Model::cube(device, size),
+      ModelPrimitive::Plane { subdivisions } => Model::plane(device, size, *subdivisions),
+    }
+  }
+
+  pub fn capture(&mut self, path: &Path) -> Result<()> {
+    let device = &self.device;
+    let queue = &self.queue;
+    let renderer = &mut self.renderer;
+    let cube_model = &self.cube_model;
+    let models = &self.models;
+
+    capture::capture_png(device, queue, &self.config, path, |view| {
+      renderer.render(device, queue, view, cube_model, models, None);
+    })
+  }
+
+  // Orbits the light around the center of everything loaded rather than the origin
+  pub fn center_light_on_models(&mut self) {
+    let bounds = self.models.iter().fold(Aabb::empty(), |bounds, model| bounds.union(model.world_bounds()));
+
+    if !bounds.is_empty() {
+      self.renderer.set_light_orbit_center(bounds.center());
+    }
+  }
+
+  // Drops the outline, the orbit pivot stays where it is
+  fn clear_selection(&mut self) {
+    self.selected_instance = None;
+    self.renderer.set_selected_instance(None);
+  }
+
+  // Tracks the cursor in physical pixels and reports its ground position while F8 is on
+  pub fn cursor_moved(&mut self, x: f64, y: f64) {
+    self.cursor_position = Vector2::new(x as f32, y as f32);
+    if self.show_ground_cursor {
+      self.log_ground_cursor();
+    }
+  }
+
+  pub fn enable_ui(&mut self, window: &Window) {
+    self.ui = Some(Ui::new(&self.device, self.config.format, window));
+  }
+
+  // Point where the ray under the cursor crosses the y = 0 plane, if it does in front of the camera
+  pub fn ground_point(&self) -> Option<Point3<f32>> {
+    let [x, y, width, height] = self.renderer.viewport().map(|value| value as f32);
+    let ndc = Vector2::new(
+      2.0 * (self.cursor_position.x - x) / width - 1.0,
+      1.0 - 2.0 * (self.cursor_position.y - y) / height,
+    );
+    let (near, far) = self.renderer.unproject(&self.camera_rig.camera, ndc)?;
+    let direction = far - near;
+
+    if direction.y.abs() < f32::EPSILON {
+      return None;
+    }
+
+    let t = -near.y / direction.y;
+
+    if t < 0.0 {
+      None
+    } else {
+      Some(near + direction * t)
+    }
+  }
+
+  // Loads the bundled textured cube used to draw the light, from res_dir or else the build's resource copy
+  pub fn load_default_model(&mut self, res_dir: Option<&Path>) -> Result<()> {
+    let res_dir = match res_dir {
+      Some(dir) => dir.to_path_buf(),
+      None => {
+        let build_dir = Path::new(env!("OUT_DIR")).join("res");
+
+        if !build_dir.is_dir() {
+          bail!("{} doesn't exist, pass --res-dir or set RR_RES_DIR", build_dir.display());
+        }
+        build_dir
+      }
     };
 
-    self.models.push(model);
+    self.cube_model = Model::load(&self.device, res_dir.join("cube.obj"))?;
+
+    Ok(())
   }
 
-  pub fn add_surface(&mut self, count: u32, size: f32, height_max: f32) {
-    let model = Model::surface(&self.device, count, size, height_max);
+  pub fn help_lines(&self) -> Vec<String> {
+    KEY_BINDINGS.iter().map(|binding| {
+      let state = binding.state.map_or("", |state| if state(self) { " [on]" } else { " [off]" });
 
-    self.models.push(model);
+      format!("{:<14} {}{}", binding.keys, binding.action, state)
+    }).chain(self.mesh_lines()).chain(std::iter::once(self.light_line())).collect()
+  }
+
+  // Adds every model in the scene, then applies its camera, light and background
+  pub fn load_scene(&mut self, scene: &Scene) -> Result<()> {
+    for scene_model in &scene.models {
+      let model = self.build_model(&scene_model.source)?;
+      let instances = if scene_model.instances.is_empty() {
+        self.instances.clone()
+      } else {
+        scene_model.instances.iter().map(Instance::from).collect()
+      };
+
+      self.push_instanced_model(model, instances);
+      self.remember_source(scene_model.source.clone());
+    }
+
+    if let Some(camera) = &scene.camera {
+      self.set_camera(camera.position, camera.target);
+    }
+    if let Some(light) = &scene.light {
+      if let Some(position) = light.position {
+        self.renderer.set_light_position(position);
+      }
+      if let Some(color) = light.color {
+        self.apply_light_color(color);
+      }
+    }
+    if let Some(background) = scene.background {
+      self.renderer.set_background(background);
+    }
+
+    Ok(())
+  }
+
+  // Loads a model on a headless device and prints its geometry and load time
+  pub async fn info(path: &Path, import_options: &ImportOptions) -> Result<()> {
+    let instance = wgpu::Instance::new(wgpu::Backends::all());
+    let (_, device, _) = request_device(&instance, None).await?;
+    let start = std::time::Instant::now();
+    let (_, info) = Model::load_with_info(&device, path, import_options)?;
+    let load_time = start.elapsed();
+    let yes_no = |value| if value { "yes" } else { "no" };
+
+    println!("{}", path.display());
+    println!("  meshes: {}", info.meshes);
+    println!("  vertices: {}", info.vertices);
+    println!("  triangles: {}", info.triangles);
+    println!("  normals: {}", yes_no(info.has_normals));
+    println!("  uvs: {}", yes_no(info.has_uvs));
+    println!("  load time: {:.2} ms", load_time.as_secs_f64() * 1000.0);
+
+    Ok(())
   }
 
   pub fn input(&mut self, event: &DeviceEvent) -> bool {
+    if self.ui.as_ref().map_or(false, Ui::wants_input) || self.camera_path.is_some() || self.turntable.is_some() {
+      return false;
+    }
+
     match event {
       DeviceEvent::Key(
         KeyboardInput {
@@ -150,12 +454,110 @@ impl State {
         }
       ) => {
         match (*key, *state) {
+          (VirtualKeyCode::S, ElementState::Pressed) if self.control_held => {
+            let path = capture::timestamped_path(&self.screenshot_dir, "scene", "json");
+
+            match self.save_scene(&path) {
+              Ok(()) => log::info!("Saved scene to {}", path.display()),
+              Err(error) => log::error!("Failed to save scene to {}: {:?}", path.display(), error),
+            }
+          }
+          (key, ElementState::Pressed) if self.control_held && MESH_KEYS.contains(&key) => {
+            if let Some(index) = MESH_KEYS.iter().position(|&mesh_key| mesh_key == key) {
+              self.toggle_mesh(index);
+            }
+          }
+          (VirtualKeyCode::F8, ElementState::Pressed) => {
+            self.show_ground_cursor = !self.show_ground_cursor;
+            if self.show_ground_cursor {
+              self.log_ground_cursor();
+            }
+          }
+          (VirtualKeyCode::F9, ElementState::Pressed) => {
+            self.renderer.toggle_facing();
+          }
+          (VirtualKeyCode::F10, ElementState::Pressed) => {
+            self.renderer.toggle_instance_colors();
+          }
+          (VirtualKeyCode::F11, ElementState::Pressed) => {
+            self.renderer.toggle_normals();
+          }
+          (VirtualKeyCode::F1, ElementState::Pressed) | (VirtualKeyCode::H, ElementState::Pressed) => {
+            self.toggle_help();
+          }
+          (VirtualKeyCode::B, ElementState::Pressed) => {
+            self.renderer.toggle_bounds();
+          }
+          (VirtualKeyCode::Back, ElementState::Pressed) => {
+            self.clear_selection();
+          }
+          (VirtualKeyCode::Comma, ElementState::Pressed) => {
+            self.renderer.move_clip_plane(-CLIP_PLANE_STEP);
+          }
+          (VirtualKeyCode::Period, ElementState::Pressed) => {
+            self.renderer.move_clip_plane(CLIP_PLANE_STEP);
+          }
+          (VirtualKeyCode::LBracket, ElementState::Pressed) => {
+            self.set_explode((self.explode - EXPLODE_STEP).max(0.0));
+          }
+          (VirtualKeyCode::RBracket, ElementState::Pressed) => {
+            self.set_explode(self.explode + EXPLODE_STEP);
+          }
+          (VirtualKeyCode::Equals, ElementState::Pressed) | (VirtualKeyCode::NumpadAdd, ElementState::Pressed) => {
+            self.renderer.adjust_exposure(EXPOSURE_STEP);
+            log::info!("Exposure {:+.2} EV", self.renderer.exposure());
+          }
+          (VirtualKeyCode::Minus, ElementState::Pressed) | (VirtualKeyCode::NumpadSubtract, ElementState::Pressed) => {
+            self.renderer.adjust_exposure(-EXPOSURE_STEP);
+            log::info!("Exposure {:+.2} EV", self.renderer.exposure());
+          }
+          (VirtualKeyCode::X, ElementState::Pressed) => {
+            self.renderer.toggle_cull_backfaces();
+          }
+          (VirtualKeyCode::G, ElementState::Pressed) => {
+            self.renderer.toggle_axes();
+          }
+          (VirtualKeyCode::F, ElementState::Pressed) => {
+            self.renderer.toggle_frustum(&self.device, &self.camera_rig.camera);
+          }
+          (VirtualKeyCode::I, ElementState::Pressed) => {
+            self.nudge_light([0.0, 0.0, -1.0]);
+          }
+          (VirtualKeyCode::J, ElementState::Pressed) => {
+            self.nudge_light([-1.0, 0.0, 0.0]);
+          }
+          (VirtualKeyCode::K, ElementState::Pressed) => {
+            self.nudge_light([0.0, 0.0, 1.0]);
+          }
           (VirtualKeyCode::L, ElementState::Pressed) => {
+            self.nudge_light([1.0, 0.0, 0.0]);
+          }
+          (VirtualKeyCode::M, ElementState::Pressed) => {
+            self.renderer.toggle_minimap();
+          }
+          (VirtualKeyCode::O, ElementState::Pressed) => {
+            self.nudge_light([0.0, 1.0, 0.0]);
+          }
+          (VirtualKeyCode::U, ElementState::Pressed) => {
+            self.nudge_light([0.0, -1.0, 0.0]);
+          }
+          (VirtualKeyCode::V, ElementState::Pressed) => {
             self.renderer.toggle_light_render();
           }
+          (VirtualKeyCode::P, ElementState::Pressed) => {
+            self.screenshot();
+          }
           (VirtualKeyCode::R, ElementState::Pressed) => {
             self.renderer.toggle_light_rotation();
           }
+          (VirtualKeyCode::Return, ElementState::Pressed) => {
+            if let Err(error) = self.rebuild_last_model() {
+              log::error!("Couldn't rebuild the model: {:?}", error);
+            }
+          }
+          (VirtualKeyCode::Tab, ElementState::Pressed) => {
+            self.select_next_instance();
+          }
           _ => {
             self.camera_rig.controller.process_keyboard(*key, *state);
           }
@@ -173,8 +575,16 @@ impl State {
         self.mouse_pressed = *state == ElementState::Pressed;
         true
       }
+      DeviceEvent::Button {
+        button: 2,
+        state,
+      } => {
+        self.pan_pressed = *state == ElementState::Pressed;
+        self.camera_rig.controller.set_panning(self.pan_pressed);
+        true
+      }
       DeviceEvent::MouseMotion { delta } => {
-        if self.mouse_pressed {
+        if self.mouse_pressed || self.pan_pressed {
           self.camera_rig.controller.process_mouse(delta.0, delta.1);
         }
         true
@@ -183,14 +593,152 @@ impl State {
     }
   }
 
+  fn light_line(&self) -> String {
+    let [x, y, z] = self.renderer.light_position();
+
+    format!("Light at ({:.3}, {:.3}, {:.3})", x, y, z)
+  }
+
+  fn log_ground_cursor(&self) {
+    match self.ground_point() {
+      Some(point) => log::info!("Cursor on ground at ({:.3}, 0.000, {:.3})", point.x, point.z),
+      None => log::info!("Cursor is off the ground plane"),
+    }
+  }
+
+  // Visibility of each mesh, for models with more than one
+  fn mesh_lines(&self) -> Vec<String> {
+    self.models.iter().enumerate()
+      .filter(|(_, model)| model.meshes.len() > 1)
+      .map(|(model_index, model)| {
+        let meshes = model.meshes.iter().enumerate()
+          .map(|(index, mesh)| format!("{} {}{}", index + 1, mesh.name, if mesh.visible { "" } else { " [hidden]" }))
+          .collect::<Vec<_>>();
+
+        format!("Model {} meshes: {}", model_index + 1, meshes.join(", "))
+      })
+      .collect()
+  }
+
+  // From the window's own modifier state, so releasing one Ctrl while the other is held keeps it held
+  pub fn modifiers_changed(&mut self, modifiers: ModifiersState) {
+    self.control_held = modifiers.ctrl();
+  }
+
+  // Steps the light along a world axis, unless it is animated or following the camera
+  fn nudge_light(&mut self, direction: [f32; 3]) {
+    if self.renderer.move_light(direction.map(|axis| axis * self.light_step)) {
+      log::info!("{}", self.light_line());
+    }
+  }
+
+  // Re-targets the orbit pivot on the center of an instance, counting through every model's instances in order
+  pub fn orbit_around(&mut self, index: usize) {
+    let center = self.models.iter()
+      .flat_map(|model| model.instances.instances.iter().map(move |instance| (model, instance)))
+      .nth(index)
+      .map(|(model, instance)| {
+        instance.position + instance.rotation * (model.bounds.center() * instance.scale)
+      });
+
+    match center {
+      Some(center) => {
+        self.selected_instance = Some(index);
+        self.renderer.set_selected_instance(self.selected_instance);
+        self.camera_rig.controller.retarget(Point3::from_vec(center), true);
+      }
+      None => log::warn!("There is no instance {} to orbit around", index),
+    }
+  }
+
   pub fn prompt_for_file(&mut self) -> Result<()> {
     if let nfd::Response::Okay(path) = nfd::open_file_dialog(None, None)? {
-      self.models.push(Model::load(&self.device, path)?);
+      let source = ModelSource::File { path: PathBuf::from(path) };
+      let model = self.build_model(&source)?;
+
+      self.push_model(model);
+      self.remember_source(source);
+    }
+    Ok(())
+  }
+
+  // Models added without explicit transforms share the default instance set
+  fn push_model(&mut self, model: Model) {
+    self.push_instanced_model(model, self.instances.clone());
+  }
+
+  fn push_instanced_model(&mut self, mut model: Model, instances: Vec<Instance>) {
+    model.set_instances(&self.device, &self.queue, instances);
+    model.set_explode(&self.queue, self.explode);
+    model.set_draw_limit(self.draw_limit);
+    self.models.push(model);
+    self.renderer.reset_accumulation();
+  }
+
+  fn primitive_source(primitive: ModelPrimitive, size: f32) -> ModelSource {
+    match primitive {
+      ModelPrimitive::Cube => ModelSource::Cube { size },
+      ModelPrimitive::Plane { subdivisions } => ModelSource::Plane { size, subdivisions },
+    }
+  }
+
+  // Runs the builder of the most recently added model again, which regenerates random surfaces
+  // and rereads files. The model keeps its place, instances, mesh colors and visibility.
+  pub fn rebuild_last_model(&mut self) -> Result<()> {
+    let (index, source) = match self.model_sources.last() {
+      Some((index, source)) => (*index, source.clone()),
+      None => return Ok(()),
+    };
+    let mut model = self.build_model(&source)?;
+
+    if let Some(old_model) = self.models.get(index) {
+      model.set_instances(&self.device, &self.queue, old_model.instances.instances.clone());
+      model.set_explode(&self.queue, self.explode);
+      model.set_draw_limit(self.draw_limit);
+      for &(mesh_index, color) in &self.mesh_colors {
+        model.set_mesh_color(&self.queue, mesh_index, color);
+      }
+      for &(mesh_index, color) in &self.mesh_emissive {
+        model.set_mesh_emissive(&self.queue, mesh_index, color);
+      }
+      for (mesh, old_mesh) in model.meshes.iter_mut().zip(&old_model.meshes) {
+        mesh.visible = old_mesh.visible;
+      }
+      self.models[index] = model;
+      self.renderer.reset_accumulation();
     }
+
     Ok(())
   }
 
+  // Captures the next frame of an active recording
+  pub fn record_frame(&mut self) -> Result<()> {
+    let path = match &mut self.recorder {
+      Some(recorder) if !recorder.finished() => recorder.next_path(),
+      _ => return Ok(()),
+    };
+
+    // Saves the frame just presented rather than drawing it again
+    match self.renderer.frame_texture() {
+      Some(frame) => capture::save_png(&self.device, &self.queue, &self.config, &frame.texture, &path),
+      None => self.capture(&path),
+    }
+  }
+
+  pub fn recording_finished(&self) -> bool {
+    self.recorder.as_ref().map_or(false, FrameRecorder::finished)
+  }
+
+  fn remember_source(&mut self, source: ModelSource) {
+    self.model_sources.push((self.models.len() - 1, source));
+  }
+
   pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+    // A minimized window has no surface to draw to
+    if self.minimized {
+      return Ok(());
+    }
+
     let output = self.surface.get_current_texture()?;
     let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
 
@@ -200,7 +748,7 @@ impl State {
       &view,
       &self.cube_model,
       &self.models,
-      &self.instance_buffer,
+      self.ui.as_mut(),
     );
 
     output.present();
@@ -209,18 +757,501 @@ impl State {
   }
 
   pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-    if new_size.width > 0 && new_size.height > 0 {
+    self.minimized = new_size.width == 0 || new_size.height == 0;
+    if !self.minimized {
       self.size = new_size;
       self.config.width = new_size.width;
       self.config.height = new_size.height;
       self.surface.configure(&self.device, &self.config);
       self.renderer.resize(&self.device, &self.config);
+      // The projection aspect changed, so the next frame mustn't use the old camera matrix
+      self.renderer.update_camera_uniform(&self.camera_rig.camera);
+    }
+  }
+
+  // Describes the models, camera, light and background as a scene that load_scene rebuilds.
+  // File paths are made absolute so the scene can be saved anywhere.
+  pub fn save_scene(&self, path: &Path) -> Result<()> {
+    let models = self.model_sources.iter().filter_map(|(index, source)| {
+      let model = self.models.get(*index)?;
+      let source = match source {
+        ModelSource::File { path } => ModelSource::File { path: path.canonicalize().unwrap_or_else(|_| path.clone()) },
+        ModelSource::Heightmap { path, size, subdivisions, scale } => ModelSource::Heightmap {
+          path: path.canonicalize().unwrap_or_else(|_| path.clone()),
+          size: *size,
+          subdivisions: *subdivisions,
+          scale: *scale,
+        },
+        ModelSource::Merged { parts } => ModelSource::Merged {
+          parts: parts.iter().map(|part| ScenePart {
+            offset: part.offset,
+            path: part.path.canonicalize().unwrap_or_else(|_| part.path.clone()),
+          }).collect(),
+        },
+        source => source.clone(),
+      };
+
+      Some(SceneModel {
+        instances: model.instances.instances.iter().map(SceneInstance::from).collect(),
+        source,
+      })
+    }).collect();
+    let camera = &self.camera_rig.camera;
+    let scene = Scene {
+      background: Some(self.renderer.background()),
+      camera: Some(SceneCamera { position: camera.get_position().into(), target: camera.target().into() }),
+      light: Some(SceneLight {
+        color: Some(self.light_color),
+        position: Some(self.renderer.light_position()),
+      }),
+      models,
+    };
+
+    scene.save(path)
+  }
+
+  pub fn screenshot(&mut self) {
+    let path = capture::timestamped_path(&self.screenshot_dir, "screenshot", "png");
+
+    match self.capture(&path) {
+      Ok(()) => log::info!("Saved screenshot to {}", path.display()),
+      Err(error) => log::error!("Failed to save screenshot to {}: {:?}", path.display(), error),
     }
   }
 
+  fn select_next_instance(&mut self) {
+    let count = self.models.iter().map(|model| model.instances.instances.len()).sum::<usize>();
+
+    if count > 0 {
+      self.orbit_around(self.selected_instance.map_or(0, |index| (index + 1) % count));
+    }
+  }
+
+  pub fn set_accumulate(&mut self, accumulate: bool) {
+    self.renderer.set_accumulate(&self.device, &self.config, accumulate);
+  }
+
+  pub fn camera_path_finished(&self) -> bool {
+    self.camera_path.as_ref().map_or(false, CameraPath::finished)
+  }
+
+  pub fn turntable_finished(&self) -> bool {
+    self.turntable.as_ref().map_or(false, Turntable::finished)
+  }
+
+  pub fn set_aspect_lock(&mut self, aspect_lock: Option<f32>) {
+    self.renderer.set_aspect_lock(aspect_lock);
+  }
+
+  pub fn set_camera(&mut self, eye: [f32; 3], target: [f32; 3]) {
+    self.camera_rig.camera.look_at(eye.into(), target.into());
+    self.renderer.update_camera_uniform(&self.camera_rig.camera);
+  }
+
+  // Plays the path back instead of taking camera input
+  pub fn set_camera_path(&mut self, path: CameraPath) {
+    self.camera_path = Some(path);
+  }
+
+  pub fn set_checker_background(&mut self, checker_background: bool) {
+    self.renderer.set_checker_background(checker_background);
+  }
+
+  pub fn set_clear(&mut self, clear_color: bool, clear_depth: bool) {
+    self.renderer.set_clear(clear_color, clear_depth);
+  }
+
+  pub fn set_clip_plane(&mut self, plane: Option<[f32; 4]>) {
+    self.renderer.set_clip_plane(plane);
+  }
+
+  pub fn set_cull_backfaces(&mut self, cull_backfaces: bool) {
+    self.renderer.set_cull_backfaces(cull_backfaces);
+  }
+
+  // Starts the dolly zoom from the view of its first frame, keeping its width at the orbit target
+  pub fn set_dolly_zoom(&mut self, dolly_zoom: bool) {
+    self.dolly_zoom = dolly_zoom.then(DollyZoom::default);
+  }
+
+  // Caps the instances drawn per model, to sweep draw counts while profiling
+  pub fn set_draw_limit(&mut self, limit: Option<u32>) {
+    self.draw_limit = limit;
+    for model in &mut self.models {
+      model.set_draw_limit(limit);
+    }
+    self.renderer.reset_accumulation();
+  }
+
+  pub fn set_explode(&mut self, factor: f32) {
+    self.explode = factor;
+    for model in &mut self.models {
+      model.set_explode(&self.queue, factor);
+    }
+    self.renderer.reset_accumulation();
+  }
+
+  pub fn set_exposure(&mut self, exposure: f32) {
+    self.renderer.set_exposure(exposure);
+  }
+
+  pub fn set_fake_ao(&mut self, strength: f32) {
+    self.renderer.set_fake_ao(strength);
+  }
+
+  pub fn set_fog(&mut self, color: Option<[f32; 3]>, density: f32, start: f32, end: f32) {
+    self.renderer.set_fog(color, density, start, end);
+  }
+
+  pub fn set_gpu_debug(&mut self, gpu_debug: bool) {
+    self.renderer.set_gpu_debug(gpu_debug);
+  }
+
+  pub fn set_grid(&mut self, grid: Option<Grid>) {
+    self.renderer.set_grid(&self.device, grid);
+  }
+
+  pub fn set_headlight(&mut self, headlight: bool) {
+    self.renderer.set_headlight(headlight);
+  }
+
+  pub fn set_import_options(&mut self, import_options: ImportOptions) {
+    self.import_options = import_options;
+  }
+
+  pub fn set_instances(&mut self, instances: Vec<Instance>) {
+    for model in &mut self.models {
+      model.set_instances(&self.device, &self.queue, instances.clone());
+    }
+    self.instances = instances;
+    self.renderer.reset_accumulation();
+  }
+
+  // None keeps the current color. The intensity also scales colors set later by the sun or a scene.
+  pub fn set_light_color(&mut self, color: Option<[f32; 3]>, intensity: f32) {
+    self.light_intensity = intensity.max(0.0);
+    self.apply_light_color(color.unwrap_or(self.light_color));
+  }
+
+  pub fn set_light_orbit(&mut self, radius: Option<f32>, height: Option<f32>, speed: f32, animate: bool) {
+    self.renderer.set_light_orbit(radius, height, Deg(speed));
+    self.renderer.set_light_rotation(animate);
+  }
+
+  // Puts the light where the sun would be, overriding its position and color
+  pub fn set_sun(&mut self, time_of_day: f32, latitude: f32) {
+    let (position, color) = light::sun(time_of_day, Deg(latitude));
+
+    self.renderer.set_light_position(position);
+    self.apply_light_color(color);
+  }
+
+  pub fn set_light_step(&mut self, step: f32) {
+    self.light_step = step;
+  }
+
+  // Turns the camera once around its target over the given number of frames, one step per frame
+  // whatever the frame time, starting from the current view
+  pub fn set_loop_frames(&mut self, frames: Option<u32>) {
+    self.turntable = frames.filter(|&frames| frames > 0).map(Turntable::new);
+  }
+
+  // Longer frames, like a stall while the window is dragged, advance animation by this much only
+  pub fn set_max_dt(&mut self, max_dt: std::time::Duration) {
+    self.max_dt = max_dt;
+  }
+
+  pub fn set_material(&mut self, shading_model: ShadingModel, metallic: f32, roughness: f32) {
+    self.renderer.set_shading_model(shading_model);
+    self.renderer.set_material(metallic, roughness);
+  }
+
+  pub fn set_mesh_colors(&mut self, colors: &[(usize, [f32; 3])]) {
+    self.mesh_colors = colors.to_vec();
+    for model in &mut self.models {
+      for &(index, color) in colors {
+        model.set_mesh_color(&self.queue, index, color);
+      }
+    }
+  }
+
+  pub fn set_mesh_emissive(&mut self, colors: &[(usize, [f32; 3])]) {
+    self.mesh_emissive = colors.to_vec();
+    for model in &mut self.models {
+      for &(index, color) in colors {
+        model.set_mesh_emissive(&self.queue, index, color);
+      }
+    }
+  }
+
+  pub fn set_mouse_inversion(&mut self, invert_x: bool, invert_y: bool) {
+    self.camera_rig.controller.set_inversion(invert_x, invert_y);
+  }
+
+  pub fn set_normal_length(&mut self, length: f32) {
+    self.normal_length = length;
+  }
+
+  pub fn set_orbit_step(&mut self, degrees: f32) {
+    self.camera_rig.controller.set_orbit_step(Deg(degrees));
+  }
+
+  pub fn set_point_mode(&mut self, render_points: bool, size: f32) {
+    self.renderer.set_point_mode(render_points);
+    self.renderer.set_point_size(size);
+  }
+
+  pub fn set_screenshot_dir(&mut self, dir: PathBuf) {
+    self.screenshot_dir = dir;
+  }
+
+  pub fn set_specular(&mut self, shininess: f32, strength: f32) {
+    self.renderer.set_specular(shininess, strength);
+  }
+
+  pub fn set_split(&mut self, split_shading_model: Option<ShadingModel>) {
+    self.renderer.set_split(split_shading_model);
+  }
+
+  // wgpu can't report whether the compositor honors surface alpha, so unsupported platforms just stay opaque
+  pub fn set_transparent(&mut self, transparent: bool) {
+    if transparent {
+      log::warn!("Transparent windows need compositor support, the background stays opaque without it");
+    }
+    self.renderer.set_transparent(transparent);
+  }
+
+  pub fn set_walk(&mut self, eye_height: Option<f32>) {
+    self.camera_rig.controller.set_walk(eye_height);
+  }
+
+  pub fn set_wireframe(&mut self, render_wireframe: bool) {
+    self.renderer.set_wireframe(render_wireframe);
+  }
+
+  pub fn set_wire_width(&mut self, width: Option<f32>) {
+    self.renderer.set_wire_width(width);
+  }
+
+  pub fn set_wrap(&mut self, wrap: f32) {
+    self.renderer.set_wrap(wrap);
+  }
+
+  pub fn set_xray(&mut self, render_xray: bool) {
+    self.renderer.set_xray(render_xray);
+  }
+
+  pub fn set_zoom_mode(&mut self, zoom_mode: ZoomMode) {
+    self.camera_rig.controller.set_zoom_mode(zoom_mode);
+  }
+
+  pub fn start_recording(&mut self, dir: PathBuf, count: u32) -> Result<()> {
+    self.recorder = Some(FrameRecorder::new(dir, count)?);
+    self.renderer.set_frame_target(&self.device, &self.config, true);
+
+    Ok(())
+  }
+
+  pub fn start_stats(&mut self, path: PathBuf) {
+    self.stats = Some(FrameStats::new(path));
+  }
+
+  // Builds every pipeline against an offscreen target and reports which ones failed
+  pub async fn validate() -> Result<bool> {
+    let instance = wgpu::Instance::new(wgpu::Backends::all());
+    let (adapter, device, _) = request_device(&instance, None).await?;
+    let config = wgpu::SurfaceConfiguration {
+      usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+      format: OFFSCREEN_FORMAT,
+      width: 1,
+      height: 1,
+      present_mode: wgpu::PresentMode::Fifo,
+    };
+    let options = RenderOptions {
+      deferred: true,
+      depth_format: Some(Texture::select_depth_format(&adapter, Texture::DEPTH_FORMAT)),
+    };
+    let renderer = Renderer::new(&device, &config, &options);
+
+    println!("Validating pipelines on {}", adapter.get_info().name);
+
+    for report in renderer.pipeline_reports() {
+      match &report.error {
+        None => println!("  {}: ok", report.label),
+        Some(error) => println!("  {}: failed\n{}", report.label, error),
+      }
+    }
+
+    Ok(renderer.pipeline_reports().iter().all(|report| report.error.is_none()))
+  }
+
+  // Renders one view of a model, scaled and framed to fill a size x size PNG, without a window
+  pub async fn thumbnail(input: &Path, output: &Path, size: u32, import_options: &ImportOptions) -> Result<()> {
+    let instance = wgpu::Instance::new(wgpu::Backends::all());
+    let (adapter, device, queue) = request_device(&instance, None).await?;
+    let config = wgpu::SurfaceConfiguration {
+      usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+      format: OFFSCREEN_FORMAT,
+      width: size,
+      height: size,
+      present_mode: wgpu::PresentMode::Fifo,
+    };
+    let options = RenderOptions {
+      depth_format: Some(Texture::select_depth_format(&adapter, Texture::DEPTH_FORMAT)),
+      ..RenderOptions::default()
+    };
+    let mut renderer = Renderer::new(&device, &config, &options);
+    // Normalized so any model sits well inside the projection's depth range
+    let import_options = ImportOptions {
+      normalize: Some(2.0),
+      recenter: true,
+      ..import_options.clone()
+    };
+    let model = Model::load_with(&device, input, &import_options)?;
+
+    if model.bounds.is_empty() {
+      bail!("{} has no geometry to frame", input.display());
+    }
+
+    // Backs off along a three quarter view until the bounding sphere fits the field of view
+    let center = Point3::from_vec(model.bounds.center());
+    let radius = model.bounds.size().magnitude() / 2.0;
+    let distance = radius / Rad::from(renderer.fov() / 2.0).sin();
+    let direction = Vector3::new(1.0, 0.8, 1.0).normalize();
+    let eye = center + direction * distance;
+    let mut camera = OrbitCamera::new(eye);
+    let light_model = Model::cube(&device, 2.0);
+
+    camera.look_at(eye, center);
+    renderer.set_light_position((center + Vector3::new(1.0, 2.0, 1.5) * distance).into());
+    renderer.update_camera_uniform(&camera);
+    renderer.update(&queue, std::time::Duration::ZERO);
+
+    capture::capture_png(&device, &queue, &config, output, |view| {
+      renderer.render(&device, &queue, view, &light_model, std::slice::from_ref(&model), None);
+    })
+  }
+
+  // Lists the bindings in the UI when it is enabled, otherwise in the log
+  fn toggle_help(&mut self) {
+    self.show_help = !self.show_help;
+    if self.show_help && self.ui.is_none() {
+      for line in self.help_lines() {
+        log::info!("{}", line);
+      }
+    }
+  }
+
+  // Flips the visibility of the mesh at index in every model that has one there
+  fn toggle_mesh(&mut self, index: usize) {
+    for model in &mut self.models {
+      if let Some(visible) = model.meshes.get(index).map(|mesh| mesh.visible) {
+        model.set_mesh_visible(index, !visible);
+      }
+    }
+    self.renderer.reset_accumulation();
+  }
+
+  // Triangles drawn per frame across every model and instance
+  pub fn triangle_count(&self) -> u64 {
+    self.models.iter().map(|model| {
+      let triangles = model.meshes.iter()
+        .filter(|mesh| mesh.visible && mesh.topology == wgpu::PrimitiveTopology::TriangleList)
+        .map(|mesh| mesh.num_elements as u64 / 3)
+        .sum::<u64>();
+
+      triangles * model.instances.range().len() as u64
+    }).sum()
+  }
+
+  // Forwards a window event to the UI, returning true if the UI consumed it
+  pub fn ui_event(&mut self, event: &WindowEvent) -> bool {
+    self.ui.as_mut().map_or(false, |ui| ui.on_event(event))
+  }
+
   pub fn update(&mut self, dt: std::time::Duration) {
-    self.camera_rig.controller.update_camera(&mut self.camera_rig.camera, dt);
+    if let Some(stats) = &mut self.stats {
+      stats.record(dt);
+    }
+    // Stats keep the real frame time, animation gets the clamped one
+    let dt = dt.min(self.max_dt);
+
+    match &mut self.camera_path {
+      Some(path) => {
+        path.advance(dt);
+        let (eye, target) = path.sample();
+        self.camera_rig.camera.look_at(eye, target);
+      }
+      None => self.camera_rig.controller.update_camera(&mut self.camera_rig.camera, dt),
+    }
+    self.renderer.zoom_fov(self.camera_rig.controller.take_fov_delta());
+    if let Some(dolly_zoom) = &mut self.dolly_zoom {
+      let camera = &mut self.camera_rig.camera;
+      let target = camera.target();
+      let offset = camera.get_position() - target;
+      let (fovy, distance) = dolly_zoom.advance(dt, offset.magnitude(), self.renderer.fov());
+      let direction = offset.normalize();
+
+      self.renderer.set_fov(fovy);
+      camera.look_at(target + direction * distance, target);
+    }
+    if let Some(turntable) = &mut self.turntable {
+      let camera = &mut self.camera_rig.camera;
+      let target = camera.target();
+
+      let offset = turntable.advance(camera.get_position() - target);
+
+      camera.look_at(target + offset, target);
+    }
     self.renderer.update_camera_uniform(&self.camera_rig.camera);
     self.renderer.update(&self.queue, dt);
+
+    // Models added since the overlay was turned on get their normals here
+    if self.renderer.rendering_normals() {
+      for model in &mut self.models {
+        model.build_normals_meshes(&self.device, self.normal_length);
+      }
+    }
   }
+
+  pub fn write_stats(&self) -> Result<()> {
+    match &self.stats {
+      Some(stats) => stats.write(&self.adapter_info, self.triangle_count()),
+      None => Ok(()),
+    }
+  }
+
+  // Lays out the UI for the next frame, applying any changes to the renderer
+  pub fn update_ui(&mut self, window: &Window) {
+    let help = if self.show_help { Some(self.help_lines()) } else { None };
+
+    if let Some(ui) = &mut self.ui {
+      ui.run(window, &mut self.renderer, help.as_deref());
+    }
+  }
+}
+
+async fn request_device(
+  instance: &wgpu::Instance,
+  surface: Option<&wgpu::Surface>,
+) -> Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue)> {
+  let adapter = instance.request_adapter(
+    &wgpu::RequestAdapterOptions {
+      power_preference: wgpu::PowerPreference::default(),
+      compatible_surface: surface,
+      force_fallback_adapter: false,
+    },
+  ).await.ok_or_else(|| anyhow!("No compatible graphics adapter found"))?;
+  let (device, queue) = adapter.request_device(
+    &wgpu::DeviceDescriptor {
+      features: wgpu::Features::empty(),
+      limits: wgpu::Limits::default(),
+      label: None,
+    },
+    None,
+  ).await?;
+
+  log::debug!("Using adapter {:?}", adapter.get_info());
+
+  Ok((adapter, device, queue))
 }
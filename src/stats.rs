@@ -0,0 +1,82 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::{
+  cmp::Ordering,
+  fs,
+  path::PathBuf,
+  time::Duration,
+};
+
+// Frame times collected over a run and written out as JSON when it ends
+pub struct FrameStats {
+  frame_times: Vec<f64>,
+  path: PathBuf,
+}
+
+#[derive(Debug, Serialize)]
+struct StatsReport<'a> {
+  adapter: &'a str,
+  backend: String,
+  frames: usize,
+  triangles: u64,
+  frame_time_ms: FrameTimes,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct FrameTimes {
+  min: f64,
+  avg: f64,
+  max: f64,
+  p50: f64,
+  p95: f64,
+  p99: f64,
+}
+
+impl FrameStats {
+  pub fn new(path: PathBuf) -> Self {
+    Self {
+      frame_times: Vec::new(),
+      path,
+    }
+  }
+
+  pub fn record(&mut self, dt: Duration) {
+    self.frame_times.push(dt.as_secs_f64() * 1000.0);
+  }
+
+  pub fn write(&self, adapter: &wgpu::AdapterInfo, triangles: u64) -> Result<()> {
+    let report = StatsReport {
+      adapter: &adapter.name,
+      backend: format!("{:?}", adapter.backend),
+      frames: self.frame_times.len(),
+      triangles,
+      frame_time_ms: frame_times(&self.frame_times),
+    };
+
+    fs::write(&self.path, serde_json::to_string_pretty(&report)?)?;
+
+    Ok(())
+  }
+}
+
+fn frame_times(times: &[f64]) -> FrameTimes {
+  if times.is_empty() {
+    return FrameTimes::default();
+  }
+
+  let mut sorted = times.to_vec();
+
+  sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+  // Nearest rank percentile
+  let percentile = |p: f64| sorted[((p / 100.0 * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len()) - 1];
+
+  FrameTimes {
+    min: sorted[0],
+    avg: sorted.iter().sum::<f64>() / sorted.len() as f64,
+    max: sorted[sorted.len() - 1],
+    p50: percentile(50.0),
+    p95: percentile(95.0),
+    p99: percentile(99.0),
+  }
+}
@@ -2,6 +2,23 @@ use image::GenericImageView;
 use anyhow::*;
 use std::path::Path;
 
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum DepthFormat {
+    Depth32Float,
+    Depth24Plus,
+    Depth24PlusStencil8,
+}
+
+impl From<DepthFormat> for wgpu::TextureFormat {
+    fn from(format: DepthFormat) -> Self {
+        match format {
+            DepthFormat::Depth32Float => wgpu::TextureFormat::Depth32Float,
+            DepthFormat::Depth24Plus => wgpu::TextureFormat::Depth24Plus,
+            DepthFormat::Depth24PlusStencil8 => wgpu::TextureFormat::Depth24PlusStencil8,
+        }
+    }
+}
+
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
@@ -11,23 +28,62 @@ pub struct Texture {
 impl Texture {
     pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 
-    pub fn create_depth_texture(device: &wgpu::Device, config:&wgpu::SurfaceConfiguration, label: &str) -> Self {
+    // Falls back through the formats that need no extra device features
+    pub fn select_depth_format(adapter: &wgpu::Adapter, preferred: wgpu::TextureFormat) -> wgpu::TextureFormat {
+        let fallbacks = [
+            wgpu::TextureFormat::Depth32Float,
+            wgpu::TextureFormat::Depth24Plus,
+            wgpu::TextureFormat::Depth24PlusStencil8,
+        ];
+        let supported = |format: &wgpu::TextureFormat| {
+            let features = adapter.get_texture_format_features(*format);
+
+            format.describe().sample_type == wgpu::TextureSampleType::Depth
+                && features.allowed_usages.contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+        };
+        let format = std::iter::once(preferred)
+            .chain(fallbacks)
+            .find(supported)
+            .unwrap_or(Self::DEPTH_FORMAT);
+
+        if format != preferred {
+            log::warn!("Depth format {:?} isn't supported, using {:?}", preferred, format);
+        }
+
+        format
+    }
+
+    // Sized to the surface, so Renderer::resize gets a depth buffer matching the new frame
+    pub fn depth_descriptor<'a>(
+        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
+        label: &'a str,
+    ) -> wgpu::TextureDescriptor<'a> {
         let size = wgpu::Extent3d {
             width: config.width,
             height: config.height,
             depth_or_array_layers: 1,
         };
-        let desc = wgpu::TextureDescriptor {
+
+        wgpu::TextureDescriptor {
             label: Some(label),
             size,
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: Self::DEPTH_FORMAT,
+            format,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT
                 | wgpu::TextureUsages::TEXTURE_BINDING,
-        };
-        let texture = device.create_texture(&desc);
+        }
+    }
+
+    pub fn create_depth_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&Self::depth_descriptor(config, format, label));
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
         let sampler = device.create_sampler(
             &wgpu::SamplerDescriptor {
@@ -47,6 +103,41 @@ impl Texture {
         Self { texture, view, sampler }
     }
 
+    pub fn create_render_target(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self { texture, view, sampler }
+    }
+
     #[allow(dead_code)]
     pub fn from_bytes(
         device: &wgpu::Device,
@@ -0,0 +1,149 @@
+use cgmath::Deg;
+use egui_wgpu::renderer::{RenderPass, ScreenDescriptor};
+use winit::{event::WindowEvent, window::Window};
+
+use crate::render::Renderer;
+
+struct UiFrame {
+  paint_jobs: Vec<egui::ClippedPrimitive>,
+  screen_descriptor: ScreenDescriptor,
+  textures_delta: egui::TexturesDelta,
+}
+
+// egui overlay. The panel is laid out before each frame and painted by the renderer
+// after the scene.
+pub struct Ui {
+  context: egui::Context,
+  frame: Option<UiFrame>,
+  render_pass: RenderPass,
+  winit_state: egui_winit::State,
+}
+
+impl Ui {
+  pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, window: &Window) -> Self {
+    let max_texture_side = device.limits().max_texture_dimension_2d as usize;
+
+    Self {
+      context: egui::Context::default(),
+      frame: None,
+      render_pass: RenderPass::new(device, format, 1),
+      winit_state: egui_winit::State::new(max_texture_side, window),
+    }
+  }
+
+  // Returns true when egui used the event and the scene should ignore it
+  pub fn on_event(&mut self, event: &WindowEvent) -> bool {
+    self.winit_state.on_event(&self.context, event)
+  }
+
+  pub fn paint(
+    &mut self,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    encoder: &mut wgpu::CommandEncoder,
+    view: &wgpu::TextureView,
+  ) {
+    let frame = match self.frame.take() {
+      Some(frame) => frame,
+      None => return,
+    };
+
+    for (id, image_delta) in &frame.textures_delta.set {
+      self.render_pass.update_texture(device, queue, *id, image_delta);
+    }
+    self.render_pass.update_buffers(device, queue, &frame.paint_jobs, &frame.screen_descriptor);
+    self.render_pass.execute(encoder, view, &frame.paint_jobs, &frame.screen_descriptor, None);
+    for id in &frame.textures_delta.free {
+      self.render_pass.free_texture(id);
+    }
+  }
+
+  pub fn run(&mut self, window: &Window, renderer: &mut Renderer, help: Option<&[String]>) {
+    let input = self.winit_state.take_egui_input(window);
+    let output = self.context.run(input, |context| {
+      control_panel(context, renderer);
+      if let Some(help) = help {
+        help_window(context, help);
+      }
+    });
+    let size = window.inner_size();
+
+    self.winit_state.handle_platform_output(window, &self.context, output.platform_output);
+    self.frame = Some(UiFrame {
+      paint_jobs: self.context.tessellate(output.shapes),
+      screen_descriptor: ScreenDescriptor {
+        size_in_pixels: [size.width, size.height],
+        pixels_per_point: window.scale_factor() as f32,
+      },
+      textures_delta: output.textures_delta,
+    });
+  }
+
+  pub fn wants_input(&self) -> bool {
+    self.context.wants_pointer_input() || self.context.wants_keyboard_input()
+  }
+}
+
+fn control_panel(context: &egui::Context, renderer: &mut Renderer) {
+  egui::Window::new("Controls").show(context, |ui| {
+    let mut fov = renderer.fov().0;
+    let mut exposure = renderer.exposure();
+    let mut ambient = renderer.ambient();
+    let mut background = renderer.background();
+    let mut light_position = renderer.light_position();
+    let mut rotate_light = renderer.rotating_light();
+    let mut render_light = renderer.rendering_light();
+    let mut render_points = renderer.rendering_points();
+    let mut render_wireframe = renderer.rendering_wireframe();
+
+    if ui.add(egui::Slider::new(&mut fov, 10.0..=120.0).text("FOV")).changed() {
+      renderer.set_fov(Deg(fov));
+    }
+    if ui.add(egui::Slider::new(&mut exposure, -4.0..=4.0).text("Exposure (EV)")).changed() {
+      renderer.set_exposure(exposure);
+    }
+    ui.horizontal(|ui| {
+      if ui.color_edit_button_rgb(&mut ambient).changed() {
+        renderer.set_ambient(ambient);
+      }
+      ui.label("Ambient");
+    });
+    ui.horizontal(|ui| {
+      if ui.color_edit_button_rgb(&mut background).changed() {
+        renderer.set_background(background);
+      }
+      ui.label("Background");
+    });
+    ui.horizontal(|ui| {
+      let mut changed = false;
+
+      for value in &mut light_position {
+        changed |= ui.add(egui::DragValue::new(value).speed(0.05)).changed();
+      }
+      if changed {
+        renderer.set_light_position(light_position);
+      }
+      ui.label("Light position");
+    });
+    if ui.checkbox(&mut rotate_light, "Animate light").changed() {
+      renderer.set_light_rotation(rotate_light);
+    }
+    if ui.checkbox(&mut render_light, "Show light").changed() {
+      renderer.set_light_render(render_light);
+    }
+    if ui.checkbox(&mut render_points, "Points").changed() {
+      renderer.set_point_mode(render_points);
+    }
+    if ui.checkbox(&mut render_wireframe, "Wireframe").changed() {
+      renderer.set_wireframe(render_wireframe);
+    }
+  });
+}
+
+fn help_window(context: &egui::Context, lines: &[String]) {
+  egui::Window::new("Key bindings").show(context, |ui| {
+    for line in lines {
+      ui.monospace(line);
+    }
+  });
+}